@@ -0,0 +1,66 @@
+// where timers read the time from; the tokio clock follows tokio's paused time in
+// tests, while a manual clock drives timers without any runtime at all
+pub trait Clock: std::fmt::Debug + Send + Sync {
+    fn now(&self) -> std::time::Instant;
+
+    fn sleep_until(
+        &self,
+        deadline: std::time::Instant,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send>>;
+}
+
+pub type SharedClock = std::sync::Arc<dyn Clock>;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioClock;
+
+impl Clock for TokioClock {
+    fn now(&self) -> std::time::Instant {
+        tokio::time::Instant::now().into_std()
+    }
+
+    fn sleep_until(
+        &self,
+        deadline: std::time::Instant,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send>> {
+        Box::pin(tokio::time::sleep_until(deadline.into()))
+    }
+}
+
+// time only moves when it is advanced
+#[cfg(test)]
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: std::sync::Arc<tokio::sync::watch::Sender<std::time::Instant>>,
+}
+
+#[cfg(test)]
+impl ManualClock {
+    pub fn new() -> Self {
+        Self {
+            now: std::sync::Arc::new(tokio::sync::watch::Sender::new(std::time::Instant::now())),
+        }
+    }
+
+    pub fn advance(&self, duration: std::time::Duration) {
+        self.now.send_modify(|now| *now += duration);
+    }
+}
+
+#[cfg(test)]
+impl Clock for ManualClock {
+    fn now(&self) -> std::time::Instant {
+        *self.now.borrow()
+    }
+
+    fn sleep_until(
+        &self,
+        deadline: std::time::Instant,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send>> {
+        let mut now = self.now.subscribe();
+        Box::pin(async move {
+            // the sender lives as long as the clock, which outlives its timers
+            let _ = now.wait_for(|now| *now >= deadline).await;
+        })
+    }
+}
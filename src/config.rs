@@ -1,6 +1,7 @@
 use anyhow::Context as _;
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Mode {
     Active,
     Passive,
@@ -22,20 +23,46 @@ impl std::str::FromStr for Mode {
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct Config {
     pub local_as: crate::types::ASNum,
-    pub local_ip: std::net::Ipv4Addr,
+    pub local_ip: std::net::IpAddr,
     pub remote_as: crate::types::ASNum,
-    pub remote_ip: std::net::Ipv4Addr,
+    pub remote_ip: std::net::IpAddr,
+    pub router_id: std::net::Ipv4Addr,
     pub mode: Mode,
+    pub hold_time: crate::types::HoldTime,
+    pub connect_retry_time: std::time::Duration,
+    pub capabilities: Vec<crate::packet::capability::Capability>,
+    pub required_capabilities: Vec<crate::packet::capability::Capability>,
+    pub remote_port: u16,
+    pub listen_port: u16,
+    // the source address of outgoing connections, chosen by the kernel when unset
+    pub update_source: Option<std::net::IpAddr>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             local_as: crate::types::ASNum::from(64512),
-            local_ip: std::net::Ipv4Addr::new(127, 0, 0, 1),
+            local_ip: std::net::Ipv4Addr::new(127, 0, 0, 1).into(),
             remote_as: crate::types::ASNum::from(64513),
-            remote_ip: std::net::Ipv4Addr::new(127, 0, 0, 2),
+            remote_ip: std::net::Ipv4Addr::new(127, 0, 0, 2).into(),
+            router_id: std::net::Ipv4Addr::new(127, 0, 0, 1),
             mode: Mode::Active,
+            hold_time: crate::types::HoldTime::default(),
+            connect_retry_time: crate::constants::CONNECT_RETRY_TIME,
+            capabilities: vec![
+                crate::packet::capability::Capability::Multiprotocol(
+                    crate::types::Afi::Ipv4,
+                    crate::types::Safi::Unicast,
+                ),
+                crate::packet::capability::Capability::Multiprotocol(
+                    crate::types::Afi::Ipv6,
+                    crate::types::Safi::Unicast,
+                ),
+            ],
+            required_capabilities: vec![],
+            remote_port: crate::constants::BGP_PORT,
+            listen_port: crate::constants::BGP_PORT,
+            update_source: None,
         }
     }
 }
@@ -44,39 +71,353 @@ impl std::str::FromStr for Config {
     type Err = crate::error::ConfigParseErr;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let config: Vec<&str> = s.split(' ').collect();
-        let local_as = crate::types::ASNum::from(config[0].parse::<u16>().context(format!(
+        // `<keyword> <value>` statements may follow the positional parts in any place
+        let mut config: Vec<&str> = Vec::new();
+        let mut remote_port = crate::constants::BGP_PORT;
+        let mut listen_port = crate::constants::BGP_PORT;
+        let mut update_source = None;
+        let mut capabilities = Vec::new();
+        let mut required_capabilities = Vec::new();
+        let mut parts = s.split(' ');
+        while let Some(part) = parts.next() {
+            if !matches!(
+                part,
+                "remote-port"
+                    | "listen-port"
+                    | "update-source"
+                    | "capability"
+                    | "required-capability"
+            ) {
+                config.push(part);
+                continue;
+            }
+            let value = parts.next().context(format!(
+                "missing value of {part} statement and config is {s}"
+            ))?;
+            match part {
+                "remote-port" => {
+                    remote_port = value.parse().context(format!(
+                        "cannot parse `{value}` as remote port and config is {s}"
+                    ))?
+                }
+                "listen-port" => {
+                    listen_port = value.parse().context(format!(
+                        "cannot parse `{value}` as listen port and config is {s}"
+                    ))?
+                }
+                "update-source" => {
+                    update_source = Some(value.parse().context(format!(
+                        "cannot parse `{value}` as update source and config is {s}"
+                    ))?)
+                }
+                "capability" => capabilities.push(
+                    value
+                        .parse()
+                        .context(format!("invalid capability and config is {s}"))?,
+                ),
+                _ => required_capabilities.push(
+                    value
+                        .parse()
+                        .context(format!("invalid required capability and config is {s}"))?,
+                ),
+            }
+        }
+        // AS numbers, addresses and mode are required, the rest is optional
+        if !(5..=8).contains(&config.len()) {
+            return Err(crate::error::ConfigParseErr::from(anyhow::anyhow!(
+                "expected 5 to 8 parts but found {0} and config is {1}",
+                config.len(),
+                s
+            )));
+        }
+        let local_as: crate::types::ASNum = config[0].parse().context(format!(
             "cannot parse 1st part of config, `{0}`, \
                  as as-number and config is {1}",
             config[0], s
-        ))?);
-        let local_ip: std::net::Ipv4Addr = config[1].parse().context(format!(
+        ))?;
+        let local_ip: std::net::IpAddr = config[1].parse().context(format!(
             "cannot parse 2nd part of config, `{0}`, \
-            as as-number and config is {1}",
+                 as local address and config is {1}",
             config[1], s
         ))?;
-        let remote_as = crate::types::ASNum::from(config[2].parse::<u16>().context(format!(
+        let remote_as: crate::types::ASNum = config[2].parse().context(format!(
             "cannot parse 3rd part of config, `{0}`, \
                  as as-number and config is {1}",
             config[2], s
-        ))?);
-        let remote_ip: std::net::Ipv4Addr = config[3].parse().context(format!(
+        ))?;
+        let remote_ip: std::net::IpAddr = config[3].parse().context(format!(
             "cannot parse 4th part of config, `{0}`, \
-             as as-number and config is {1}",
+             as remote address and config is {1}",
             config[3], s
         ))?;
         let mode: Mode = config[4].parse().context(format!(
             "cannot parse 5th part of config, `{0}`, \
-             as as-number and config is {1}",
+             as mode and config is {1}",
             config[4], s
         ))?;
+        let hold_time = match config.get(5) {
+            Some(hold_time) => {
+                crate::types::HoldTime::try_from(hold_time.parse::<u16>().context(format!(
+                    "cannot parse 6th part of config, `{0}`, \
+                     as hold time and config is {1}",
+                    hold_time, s
+                ))?)?
+            }
+            None => crate::types::HoldTime::default(),
+        };
+        let connect_retry_time = match config.get(6) {
+            Some(connect_retry_time) => std::time::Duration::from_secs(
+                connect_retry_time.parse::<u64>().context(format!(
+                    "cannot parse 7th part of config, `{0}`, \
+                     as connect retry time and config is {1}",
+                    connect_retry_time, s
+                ))?,
+            ),
+            None => crate::constants::CONNECT_RETRY_TIME,
+        };
+        // the router id can only be derived from an IPv4 local address
+        let router_id = match (config.get(7), local_ip) {
+            (Some(router_id), _) => router_id.parse().context(format!(
+                "cannot parse 8th part of config, `{0}`, \
+                 as router id and config is {1}",
+                router_id, s
+            ))?,
+            (None, std::net::IpAddr::V4(local_ip)) => local_ip,
+            (None, std::net::IpAddr::V6(_)) => {
+                return Err(crate::error::ConfigParseErr::from(anyhow::anyhow!(
+                    "router id is required for IPv6 local address and config is {s}"
+                )))
+            }
+        };
 
         Ok(Self {
             local_as,
             local_ip,
             remote_as,
             remote_ip,
+            router_id,
             mode,
+            hold_time,
+            connect_retry_time,
+            remote_port,
+            listen_port,
+            update_source,
+            // the configured capabilities replace the default ones
+            capabilities: if capabilities.is_empty() {
+                Config::default().capabilities
+            } else {
+                capabilities
+            },
+            required_capabilities,
+        })
+    }
+}
+
+// settings shared by every neighbor of the speaker, read from a TOML file such as
+//
+// asn = 64512
+// router-id = "10.0.0.1"
+// listen-addresses = ["10.200.100.2"]
+// networks = ["10.100.210.0/24"]
+//
+// [[neighbors]]
+// address = "10.200.100.3"
+// remote-as = 64513
+// mode = "passive"
+// capabilities = ["ipv4-unicast", "ipv6-unicast"]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpeakerConfig {
+    pub asn: crate::types::ASNum,
+    pub router_id: std::net::Ipv4Addr,
+    pub listen_addresses: Vec<std::net::IpAddr>,
+    // prefixes originated by this speaker
+    pub networks: Vec<crate::packet::prefix::Prefix>,
+    // the kernel routing table and protocol number of installed routes
+    pub fib_table: u32,
+    pub fib_protocol: u8,
+    pub neighbors: Vec<Config>,
+}
+
+// a speaker with just the given neighbor
+impl From<Config> for SpeakerConfig {
+    fn from(config: Config) -> Self {
+        Self {
+            asn: config.local_as,
+            router_id: config.router_id,
+            listen_addresses: vec![config.local_ip],
+            networks: vec![],
+            fib_table: crate::constants::FIB_TABLE,
+            fib_protocol: crate::constants::FIB_PROTOCOL,
+            neighbors: vec![config],
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct SpeakerFile {
+    asn: crate::types::ASNum,
+    router_id: Option<std::net::Ipv4Addr>,
+    #[serde(default)]
+    listen_addresses: Vec<std::net::IpAddr>,
+    #[serde(default)]
+    networks: Vec<crate::packet::prefix::Prefix>,
+    fib_table: Option<u32>,
+    fib_protocol: Option<u8>,
+    #[serde(default)]
+    neighbors: Vec<toml::Spanned<NeighborFile>>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct NeighborFile {
+    address: std::net::IpAddr,
+    remote_as: crate::types::ASNum,
+    local_address: Option<std::net::IpAddr>,
+    mode: Option<Mode>,
+    hold_time: Option<crate::types::HoldTime>,
+    // in seconds
+    connect_retry_time: Option<u64>,
+    remote_port: Option<u16>,
+    listen_port: Option<u16>,
+    update_source: Option<std::net::IpAddr>,
+    capabilities: Option<Vec<crate::packet::capability::Capability>>,
+    #[serde(default)]
+    required_capabilities: Vec<crate::packet::capability::Capability>,
+}
+
+impl SpeakerConfig {
+    // a speaker with the single neighbor of a command line such as
+    // `64512 127.0.0.1 64513 127.0.0.2 active network 10.100.210.0/24`, where
+    // `network`, `fib-table` and `fib-protocol` statements are taken by the
+    // speaker and everything else describes the neighbor
+    pub fn from_args(s: &str) -> Result<Self, crate::error::ConfigParseErr> {
+        let mut neighbor = Vec::new();
+        let mut networks = Vec::new();
+        let mut fib_table = crate::constants::FIB_TABLE;
+        let mut fib_protocol = crate::constants::FIB_PROTOCOL;
+        let mut parts = s.split(' ');
+        while let Some(part) = parts.next() {
+            if !matches!(part, "network" | "fib-table" | "fib-protocol") {
+                neighbor.push(part);
+                continue;
+            }
+            let value = parts.next().context(format!(
+                "missing value of {part} statement and config is {s}"
+            ))?;
+            match part {
+                "network" => networks.push(value.parse().context(format!(
+                    "cannot parse `{value}` as network prefix and config is {s}"
+                ))?),
+                "fib-table" => {
+                    fib_table = value.parse().context(format!(
+                        "cannot parse `{value}` as routing table and config is {s}"
+                    ))?
+                }
+                _ => {
+                    fib_protocol = value.parse().context(format!(
+                        "cannot parse `{value}` as routing protocol and config is {s}"
+                    ))?
+                }
+            }
+        }
+        let config: Config = neighbor.join(" ").parse()?;
+        Ok(Self {
+            networks,
+            fib_table,
+            fib_protocol,
+            ..config.into()
+        })
+    }
+
+    pub fn from_file(
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<Self, crate::error::ConfigParseErr> {
+        let path = path.as_ref();
+        let s = std::fs::read_to_string(path)
+            .context(format!("cannot read config file {}", path.display()))?;
+        Ok(s.parse()
+            .context(format!("invalid config file {}", path.display()))?)
+    }
+}
+
+impl std::str::FromStr for SpeakerConfig {
+    type Err = crate::error::ConfigParseErr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // syntax and type errors of toml already point to the offending line
+        let file: SpeakerFile = toml::from_str(s).map_err(anyhow::Error::from)?;
+        let line = |offset: usize| s[..offset].matches('\n').count() + 1;
+
+        let router_id = match file.router_id {
+            Some(router_id) => router_id,
+            None => file
+                .listen_addresses
+                .iter()
+                .find_map(|addr| match addr {
+                    std::net::IpAddr::V4(addr) => Some(*addr),
+                    std::net::IpAddr::V6(_) => None,
+                })
+                .context("router-id is required without an IPv4 listen address")?,
+        };
+        let fib_table = file.fib_table.unwrap_or(crate::constants::FIB_TABLE);
+        let fib_protocol = file.fib_protocol.unwrap_or(crate::constants::FIB_PROTOCOL);
+
+        let mut neighbors: Vec<Config> = Vec::new();
+        for neighbor in &file.neighbors {
+            let line = line(neighbor.span().start);
+            let neighbor = neighbor.get_ref();
+            if neighbors.iter().any(|n| n.remote_ip == neighbor.address) {
+                return Err(crate::error::ConfigParseErr::from(anyhow::anyhow!(
+                    "line {line}: duplicate neighbor {}",
+                    neighbor.address
+                )));
+            }
+            // the first listen address of the same family is used by default
+            let local_ip = match neighbor.local_address {
+                Some(local_address) => local_address,
+                None => file
+                    .listen_addresses
+                    .iter()
+                    .find(|addr| addr.is_ipv4() == neighbor.address.is_ipv4())
+                    .copied()
+                    .context(format!(
+                        "line {line}: no local-address or listen address for neighbor {}",
+                        neighbor.address
+                    ))?,
+            };
+
+            neighbors.push(Config {
+                local_as: file.asn,
+                local_ip,
+                remote_as: neighbor.remote_as,
+                remote_ip: neighbor.address,
+                router_id,
+                mode: neighbor.mode.clone().unwrap_or(Mode::Active),
+                hold_time: neighbor.hold_time.unwrap_or_default(),
+                connect_retry_time: neighbor.connect_retry_time.map_or(
+                    crate::constants::CONNECT_RETRY_TIME,
+                    std::time::Duration::from_secs,
+                ),
+                remote_port: neighbor.remote_port.unwrap_or(crate::constants::BGP_PORT),
+                listen_port: neighbor.listen_port.unwrap_or(crate::constants::BGP_PORT),
+                update_source: neighbor.update_source,
+                capabilities: neighbor
+                    .capabilities
+                    .clone()
+                    .unwrap_or_else(|| Config::default().capabilities),
+                required_capabilities: neighbor.required_capabilities.clone(),
+            });
+        }
+
+        Ok(Self {
+            asn: file.asn,
+            router_id,
+            listen_addresses: file.listen_addresses,
+            networks: file.networks,
+            fib_table,
+            fib_protocol,
+            neighbors,
         })
     }
 }
@@ -90,14 +431,296 @@ mod tests {
         let config: Config = "64512 127.0.0.1 65413 127.0.0.2 active".parse().unwrap();
         assert_eq!(config.local_as, crate::types::ASNum::from(64512));
         assert_eq!(config.local_ip, std::net::Ipv4Addr::new(127, 0, 0, 1));
+        assert_eq!(config.router_id, std::net::Ipv4Addr::new(127, 0, 0, 1));
         assert_eq!(config.remote_as, crate::types::ASNum::from(65413));
         assert_eq!(config.remote_ip, std::net::Ipv4Addr::new(127, 0, 0, 2));
         assert_eq!(config.mode, Mode::Active);
+        assert_eq!(config.hold_time, crate::types::HoldTime::default());
+    }
+
+    #[test]
+    fn config_can_parse_hold_time() {
+        let config: Config = "64512 127.0.0.1 65413 127.0.0.2 active 30".parse().unwrap();
+        assert_eq!(
+            config.hold_time,
+            crate::types::HoldTime::try_from(30).unwrap()
+        );
+
+        let config: Result<Config, crate::error::ConfigParseErr> =
+            "64512 127.0.0.1 65413 127.0.0.2 active 2".parse();
+        assert!(config.is_err());
+    }
+
+    #[test]
+    fn config_can_parse_connect_retry_time() {
+        let config: Config = "64512 127.0.0.1 65413 127.0.0.2 active 30 5"
+            .parse()
+            .unwrap();
+        assert_eq!(config.connect_retry_time, std::time::Duration::from_secs(5));
+    }
+
+    #[test]
+    fn config_can_parse_four_octet_as() {
+        let config: Config = "4200000000 127.0.0.1 1.10 127.0.0.2 active"
+            .parse()
+            .unwrap();
+        assert_eq!(config.local_as, crate::types::ASNum::from(4200000000));
+        assert_eq!(config.remote_as, crate::types::ASNum::from(65546));
+    }
+
+    #[test]
+    fn config_can_parse_ipv6() {
+        let config: Config = "64512 ::1 65413 ::2 active 90 120 10.0.0.1"
+            .parse()
+            .unwrap();
+        assert_eq!(config.local_ip, std::net::Ipv6Addr::LOCALHOST);
+        assert_eq!(config.remote_ip, "::2".parse::<std::net::IpAddr>().unwrap());
+        assert_eq!(config.router_id, std::net::Ipv4Addr::new(10, 0, 0, 1));
+
+        let config: Result<Config, crate::error::ConfigParseErr> =
+            "64512 ::1 65413 ::2 active".parse();
+        assert!(config.is_err());
+    }
+
+    #[test]
+    fn speaker_config_can_parse_args() {
+        let config = SpeakerConfig::from_args(
+            "64512 127.0.0.1 65413 127.0.0.2 active network 10.100.210.0/24 network 2001:db8::/32",
+        )
+        .unwrap();
+        assert_eq!(config.asn, crate::types::ASNum::from(64512));
+        assert_eq!(config.router_id, std::net::Ipv4Addr::new(127, 0, 0, 1));
+        assert_eq!(
+            config.networks,
+            vec![
+                "10.100.210.0/24".parse().unwrap(),
+                "2001:db8::/32".parse().unwrap()
+            ]
+        );
+        assert_eq!(config.neighbors.len(), 1);
+        assert_eq!(config.neighbors[0].mode, Mode::Active);
+
+        let config = SpeakerConfig::from_args(
+            "64512 127.0.0.1 65413 127.0.0.2 active network 10.0.0.0/8 30",
+        )
+        .unwrap();
+        assert_eq!(
+            config.neighbors[0].hold_time,
+            crate::types::HoldTime::try_from(30).unwrap()
+        );
+
+        assert!(
+            SpeakerConfig::from_args("64512 127.0.0.1 65413 127.0.0.2 active network").is_err()
+        );
+        assert!(SpeakerConfig::from_args(
+            "64512 127.0.0.1 65413 127.0.0.2 active network 10.0.0.0"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn speaker_config_can_parse_fib_args() {
+        let config = SpeakerConfig::from_args(
+            "64512 127.0.0.1 65413 127.0.0.2 active fib-table 100 fib-protocol 200",
+        )
+        .unwrap();
+        assert_eq!(config.fib_table, 100);
+        assert_eq!(config.fib_protocol, 200);
+
+        let config = SpeakerConfig::from_args("64512 127.0.0.1 65413 127.0.0.2 active").unwrap();
+        assert_eq!(config.fib_table, crate::constants::FIB_TABLE);
+        assert_eq!(config.fib_protocol, crate::constants::FIB_PROTOCOL);
+
+        assert!(SpeakerConfig::from_args(
+            "64512 127.0.0.1 65413 127.0.0.2 active fib-protocol 256"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn config_can_parse_ports() {
+        let config: Config =
+            "64512 127.0.0.1 65413 127.0.0.2 active remote-port 10179 listen-port 20179 update-source 127.0.0.3"
+                .parse()
+                .unwrap();
+        assert_eq!(config.remote_port, 10179);
+        assert_eq!(config.listen_port, 20179);
+        assert_eq!(
+            config.update_source,
+            Some(std::net::Ipv4Addr::new(127, 0, 0, 3).into())
+        );
+
+        let config: Result<Config, crate::error::ConfigParseErr> =
+            "64512 127.0.0.1 65413 127.0.0.2 active remote-port 65536".parse();
+        assert!(config.is_err());
     }
 
     #[test]
     fn invalid_config() {
         let config: Result<Config, crate::error::ConfigParseErr> = "foo bar baz qux quux".parse();
         assert!(config.is_err());
+
+        for s in [
+            "",
+            "64512 127.0.0.1",
+            "64512 127.0.0.1 65413 127.0.0.2",
+            "64512 127.0.0.1 65413 127.0.0.2 active 90 120 10.0.0.1 foo",
+        ] {
+            let err = s.parse::<Config>().unwrap_err();
+            assert!(err.to_string().contains("expected 5 to 8 parts"), "{err}");
+        }
+
+        let err = "64512 127.0.0.1 65413 foo active"
+            .parse::<Config>()
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("as remote address"), "{err:#}");
+    }
+
+    #[test]
+    fn speaker_config_can_parse() {
+        let config: SpeakerConfig = r#"
+asn = "1.10"
+listen-addresses = ["10.200.100.2", "2001:db8::2"]
+networks = ["10.100.210.0/24"]
+
+[[neighbors]]
+address = "10.200.100.3"
+remote-as = 64513
+
+[[neighbors]]
+address = "2001:db8::4"
+remote-as = 64514
+mode = "passive"
+hold-time = 30
+connect-retry-time = 5
+remote-port = 10179
+update-source = "2001:db8::3"
+"#
+        .parse()
+        .unwrap();
+        assert_eq!(config.asn, crate::types::ASNum::from(65546));
+        assert_eq!(config.router_id, std::net::Ipv4Addr::new(10, 200, 100, 2));
+        assert_eq!(config.networks, vec!["10.100.210.0/24".parse().unwrap()]);
+        assert_eq!(config.neighbors.len(), 2);
+
+        let neighbor = &config.neighbors[0];
+        assert_eq!(neighbor.local_as, crate::types::ASNum::from(65546));
+        assert_eq!(neighbor.local_ip, std::net::Ipv4Addr::new(10, 200, 100, 2));
+        assert_eq!(neighbor.remote_as, crate::types::ASNum::from(64513));
+        assert_eq!(neighbor.mode, Mode::Active);
+
+        let neighbor = &config.neighbors[1];
+        assert_eq!(
+            neighbor.local_ip,
+            "2001:db8::2".parse::<std::net::IpAddr>().unwrap()
+        );
+        assert_eq!(neighbor.router_id, std::net::Ipv4Addr::new(10, 200, 100, 2));
+        assert_eq!(neighbor.mode, Mode::Passive);
+        assert_eq!(
+            neighbor.hold_time,
+            crate::types::HoldTime::try_from(30).unwrap()
+        );
+        assert_eq!(
+            neighbor.connect_retry_time,
+            std::time::Duration::from_secs(5)
+        );
+        assert_eq!(neighbor.remote_port, 10179);
+        assert_eq!(neighbor.listen_port, crate::constants::BGP_PORT);
+        assert_eq!(neighbor.update_source, "2001:db8::3".parse().ok());
+    }
+
+    #[test]
+    fn speaker_config_errors_point_to_line() {
+        let err = "asn = 64512\nrouter-id = \"10.0.0.1\"\n\n[[neighbors]]\naddress = \"10.0.0.2\"\nremote-as = \"foo\"\n"
+            .parse::<SpeakerConfig>()
+            .unwrap_err();
+        assert!(err.to_string().contains("line 6"), "{err}");
+
+        let err = "asn = 64512\nrouter-id = \"10.0.0.1\"\n\n[[neighbors]]\naddress = \"10.0.0.2\"\nremote-as = 64513\nhold-time = 2\n"
+            .parse::<SpeakerConfig>()
+            .unwrap_err();
+        assert!(err.to_string().contains("line 7"), "{err}");
+
+        // neighbors without a usable local address are reported where they start
+        let err = "asn = 64512\nrouter-id = \"10.0.0.1\"\n\n[[neighbors]]\naddress = \"10.0.0.2\"\nremote-as = 64513\n"
+            .parse::<SpeakerConfig>()
+            .unwrap_err();
+        assert!(err.to_string().contains("line 4"), "{err}");
+
+        let err = "asn = 64512\nlisten-addresses = [\"::1\"]\n"
+            .parse::<SpeakerConfig>()
+            .unwrap_err();
+        assert!(err.to_string().contains("router-id"), "{err}");
+    }
+
+    #[test]
+    fn config_can_parse_capabilities() {
+        use crate::packet::capability::Capability;
+
+        let config: Config =
+            "64512 127.0.0.1 65413 127.0.0.2 active capability ipv4-unicast required-capability ipv4-unicast required-capability four-octet-as"
+                .parse()
+                .unwrap();
+        let ipv4_unicast =
+            Capability::Multiprotocol(crate::types::Afi::Ipv4, crate::types::Safi::Unicast);
+        assert_eq!(config.capabilities, vec![ipv4_unicast.clone()]);
+        assert_eq!(
+            config.required_capabilities,
+            vec![ipv4_unicast, Capability::FourOctetAs(0.into())]
+        );
+
+        let config: Config = "64512 127.0.0.1 65413 127.0.0.2 active".parse().unwrap();
+        assert_eq!(config.capabilities, Config::default().capabilities);
+        assert!(config.required_capabilities.is_empty());
+
+        let err = "64512 127.0.0.1 65413 127.0.0.2 active capability ipv4-multicast"
+            .parse::<Config>()
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("unknown capability"), "{err:#}");
+    }
+
+    #[test]
+    fn speaker_config_can_parse_capabilities() {
+        let config: SpeakerConfig = r#"
+asn = 64512
+router-id = "10.0.0.1"
+
+[[neighbors]]
+address = "10.0.0.2"
+local-address = "10.0.0.1"
+remote-as = 64513
+capabilities = ["ipv6-unicast"]
+required-capabilities = ["ipv6-unicast"]
+
+[[neighbors]]
+address = "10.0.0.3"
+local-address = "10.0.0.1"
+remote-as = 64513
+"#
+        .parse()
+        .unwrap();
+        let ipv6_unicast = crate::packet::capability::Capability::Multiprotocol(
+            crate::types::Afi::Ipv6,
+            crate::types::Safi::Unicast,
+        );
+        assert_eq!(config.neighbors[0].capabilities, vec![ipv6_unicast.clone()]);
+        assert_eq!(
+            config.neighbors[0].required_capabilities,
+            vec![ipv6_unicast]
+        );
+        assert_eq!(
+            config.neighbors[1].capabilities,
+            Config::default().capabilities
+        );
+        assert!(config.neighbors[1].required_capabilities.is_empty());
+
+        let err = "asn = 64512\nrouter-id = \"10.0.0.1\"\n\n[[neighbors]]\naddress = \"10.0.0.2\"\nremote-as = 64513\nrequired-capabilities = [\"foo\"]\n"
+            .parse::<SpeakerConfig>()
+            .unwrap_err();
+        assert!(err.to_string().contains("line 7"), "{err}");
+        assert!(
+            err.to_string().contains("unknown capability `foo`"),
+            "{err}"
+        );
     }
 }
@@ -1 +1,13 @@
+pub const BGP_PORT: u16 = 179;
 pub const HEADER_LEN: usize = 19;
+pub const MAX_MESSAGE_LEN: usize = 4096;
+// bytes an in-memory transport buffers before writes wait for the reader
+pub const MEMORY_TRANSPORT_BUFFER: usize = 65536;
+// events a speaker keeps for subscribers that fall behind
+pub const SPEAKER_EVENT_CAPACITY: usize = 1024;
+pub const CONNECT_RETRY_TIME: std::time::Duration = std::time::Duration::from_secs(120);
+pub const MAX_CONNECT_RETRY_TIME: std::time::Duration = std::time::Duration::from_secs(600);
+pub const LARGE_HOLD_TIME: std::time::Duration = std::time::Duration::from_secs(240);
+// the main routing table and RTPROT_BGP from linux/rtnetlink.h
+pub const FIB_TABLE: u32 = 254;
+pub const FIB_PROTOCOL: u8 = 186;
@@ -0,0 +1,418 @@
+pub const DEFAULT_LOCAL_PREF: u32 = 100;
+
+// the IGP cost of reaching a next hop, supplied by whatever knows the interior
+// topology
+pub trait NextHopCost: std::fmt::Debug + Send + Sync {
+    fn cost(&self, next_hop: &crate::packet::attribute::NextHop) -> u32;
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    // compares MED even between routes from different neighbor ASes
+    pub always_compare_med: bool,
+    // every next hop costs the same without one
+    pub next_hop_cost: Option<std::sync::Arc<dyn NextHopCost>>,
+}
+
+impl Options {
+    fn igp_cost(&self, c: &crate::rib::Candidate) -> u32 {
+        self.next_hop_cost
+            .as_ref()
+            .map_or(0, |next_hop_cost| next_hop_cost.cost(&c.route.next_hop))
+    }
+}
+
+// the step of the decision process at which a candidate lost
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Reason {
+    LocalPref,
+    AsPathLength,
+    Origin,
+    Med,
+    PeerType,
+    IgpCost,
+    Age,
+    RouterId,
+    PeerAddress,
+}
+
+impl std::fmt::Display for Reason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self {
+            Self::LocalPref => "lower LOCAL_PREF",
+            Self::AsPathLength => "longer AS_PATH",
+            Self::Origin => "less preferred ORIGIN",
+            Self::Med => "higher MED",
+            Self::PeerType => "iBGP route over eBGP route",
+            Self::IgpCost => "higher IGP cost to next hop",
+            Self::Age => "newer route",
+            Self::RouterId => "higher router id",
+            Self::PeerAddress => "higher peer address",
+        };
+        write!(f, "{reason}")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    pub best: std::net::IpAddr,
+    pub losers: Vec<(std::net::IpAddr, Reason)>,
+}
+
+impl std::fmt::Display for Explanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "best path from {}", self.best)?;
+        for (peer, reason) in &self.losers {
+            write!(f, ", {peer} lost: {reason}")?;
+        }
+        Ok(())
+    }
+}
+
+fn local_pref(c: &crate::rib::Candidate) -> u32 {
+    c.route.local_pref().unwrap_or(DEFAULT_LOCAL_PREF)
+}
+
+fn as_path_len(c: &crate::rib::Candidate) -> usize {
+    c.route.as_path().map_or(0, |p| p.path_len())
+}
+
+fn origin(c: &crate::rib::Candidate) -> crate::packet::attribute::Origin {
+    c.route
+        .origin()
+        .unwrap_or(crate::packet::attribute::Origin::Incomplete)
+}
+
+// a missing MED is treated as the best possible value (RFC 4271 9.1.2.2)
+fn med(c: &crate::rib::Candidate) -> u32 {
+    c.route.med().unwrap_or(0)
+}
+
+fn med_comparable(a: &crate::rib::Candidate, b: &crate::rib::Candidate, options: &Options) -> bool {
+    options.always_compare_med || a.route.neighbor_as() == b.route.neighbor_as()
+}
+
+type Step = fn(&crate::rib::Candidate, &crate::rib::Candidate, &Options) -> std::cmp::Ordering;
+
+// the steps of the decision process in order, each returning `Less` when the
+// first candidate is preferred
+fn steps() -> [(Reason, Step); 9] {
+    [
+        (Reason::LocalPref, |a, b, _| {
+            local_pref(b).cmp(&local_pref(a))
+        }),
+        (Reason::AsPathLength, |a, b, _| {
+            as_path_len(a).cmp(&as_path_len(b))
+        }),
+        (Reason::Origin, |a, b, _| origin(a).cmp(&origin(b))),
+        (Reason::Med, |a, b, _| med(a).cmp(&med(b))),
+        (Reason::PeerType, |a, b, _| {
+            b.source.ebgp.cmp(&a.source.ebgp)
+        }),
+        (Reason::IgpCost, |a, b, options| {
+            options.igp_cost(a).cmp(&options.igp_cost(b))
+        }),
+        (Reason::Age, |a, b, _| a.received.cmp(&b.received)),
+        (Reason::RouterId, |a, b, _| {
+            a.source.router_id.cmp(&b.source.router_id)
+        }),
+        (Reason::PeerAddress, |a, b, _| {
+            a.source.address.cmp(&b.source.address)
+        }),
+    ]
+}
+
+// returns `Less` when `a` is preferred over `b` together with the deciding step,
+// or `None` when the candidates cannot be told apart
+pub fn compare(
+    a: &crate::rib::Candidate,
+    b: &crate::rib::Candidate,
+    options: &Options,
+) -> Option<(std::cmp::Ordering, Reason)> {
+    steps()
+        .into_iter()
+        .filter(|(reason, _)| *reason != Reason::Med || med_comparable(a, b, options))
+        .map(|(reason, step)| (step(a, b, options), reason))
+        .find(|(ordering, _)| ordering.is_ne())
+}
+
+// returns the index of the best candidate and why each of the others lost
+//
+// every step eliminates the candidates that are beaten by another remaining one,
+// MED only being compared within the same neighbor AS unless configured
+// otherwise (RFC 4271 9.1.2.2), so the result does not depend on the order of
+// the candidates
+pub fn select(
+    candidates: &[crate::rib::Candidate],
+    options: &Options,
+) -> Option<(usize, Explanation)> {
+    let mut remaining: Vec<usize> = (0..candidates.len()).collect();
+    let mut losers = Vec::new();
+    for (reason, step) in steps() {
+        if remaining.len() <= 1 {
+            break;
+        }
+        let beaten = |i: usize| {
+            remaining.iter().any(|&j| {
+                (reason != Reason::Med || med_comparable(&candidates[i], &candidates[j], options))
+                    && step(&candidates[j], &candidates[i], options).is_lt()
+            })
+        };
+        let (eliminated, survivors): (Vec<usize>, Vec<usize>) =
+            remaining.iter().partition(|&&i| beaten(i));
+        losers.extend(eliminated.into_iter().map(|i| (i, reason)));
+        remaining = survivors;
+    }
+
+    let best = *remaining.first()?;
+    // candidates that cannot be told apart lose to the first one
+    losers.extend(remaining[1..].iter().map(|&i| (i, Reason::PeerAddress)));
+    losers.sort();
+
+    Some((
+        best,
+        Explanation {
+            best: candidates[best].source.address,
+            losers: losers
+                .into_iter()
+                .map(|(i, reason)| (candidates[i].source.address, reason))
+                .collect(),
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(
+        address: &str,
+        path_attributes: Vec<crate::packet::attribute::PathAttribute>,
+    ) -> crate::rib::Candidate {
+        crate::rib::Candidate {
+            source: crate::rib::Source {
+                address: address.parse().unwrap(),
+                router_id: "10.0.0.1".parse().unwrap(),
+                ebgp: true,
+            },
+            route: crate::route::Route {
+                prefix: crate::packet::prefix::Ipv4Prefix::new("10.0.0.0".parse().unwrap(), 8)
+                    .unwrap()
+                    .into(),
+                next_hop: crate::packet::attribute::NextHop::V4("192.0.2.1".parse().unwrap()),
+                path_attributes,
+            },
+            received: tokio::time::Instant::now(),
+        }
+    }
+
+    fn as_path(asns: &[u32]) -> crate::packet::attribute::PathAttribute {
+        crate::packet::attribute::PathAttribute::AsPath(crate::packet::attribute::AsPath::new(
+            vec![crate::packet::attribute::AsPathSegment::AsSequence(
+                asns.iter().map(|asn| (*asn).into()).collect(),
+            )],
+        ))
+    }
+
+    fn reason(a: &crate::rib::Candidate, b: &crate::rib::Candidate) -> Reason {
+        let (ordering, reason) = compare(a, b, &Options::default()).unwrap();
+        assert_eq!(ordering, std::cmp::Ordering::Less);
+        reason
+    }
+
+    #[test]
+    fn decision_steps() {
+        use crate::packet::attribute::{Origin, PathAttribute};
+
+        let base = candidate("127.0.0.2", vec![as_path(&[64513])]);
+        let mut worse = candidate("127.0.0.3", vec![as_path(&[64513])]);
+        worse.received = base.received + std::time::Duration::from_secs(1);
+        assert_eq!(reason(&base, &worse), Reason::Age);
+
+        let mut local_pref = base.clone();
+        local_pref
+            .route
+            .path_attributes
+            .push(PathAttribute::LocalPref(200));
+        assert_eq!(reason(&local_pref, &worse), Reason::LocalPref);
+
+        let longer = candidate("127.0.0.3", vec![as_path(&[64513, 64514])]);
+        assert_eq!(reason(&base, &longer), Reason::AsPathLength);
+
+        let mut igp = base.clone();
+        igp.route
+            .path_attributes
+            .push(PathAttribute::Origin(Origin::Igp));
+        let mut egp = worse.clone();
+        egp.route
+            .path_attributes
+            .push(PathAttribute::Origin(Origin::Egp));
+        assert_eq!(reason(&igp, &egp), Reason::Origin);
+
+        let mut med = worse.clone();
+        med.route
+            .path_attributes
+            .push(PathAttribute::MultiExitDisc(10));
+        assert_eq!(reason(&base, &med), Reason::Med);
+
+        let mut ibgp = worse.clone();
+        ibgp.source.ebgp = false;
+        assert_eq!(reason(&base, &ibgp), Reason::PeerType);
+
+        let mut router_id = worse.clone();
+        router_id.received = base.received;
+        router_id.source.router_id = "10.0.0.2".parse().unwrap();
+        assert_eq!(reason(&base, &router_id), Reason::RouterId);
+
+        let mut address = worse.clone();
+        address.received = base.received;
+        assert_eq!(reason(&base, &address), Reason::PeerAddress);
+    }
+
+    #[test]
+    fn med_is_compared_within_neighbor_as() {
+        use crate::packet::attribute::PathAttribute;
+
+        let mut a = candidate("127.0.0.3", vec![as_path(&[64513])]);
+        a.route
+            .path_attributes
+            .push(PathAttribute::MultiExitDisc(10));
+        let mut b = candidate("127.0.0.2", vec![as_path(&[64514])]);
+        b.route
+            .path_attributes
+            .push(PathAttribute::MultiExitDisc(20));
+        b.received = a.received;
+
+        // MED is skipped between neighbor ASes, so the lower peer address wins
+        assert_eq!(reason(&b, &a), Reason::PeerAddress);
+
+        let options = Options {
+            always_compare_med: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            compare(&a, &b, &options),
+            Some((std::cmp::Ordering::Less, Reason::Med))
+        );
+    }
+
+    #[test]
+    fn select_explains_losers() {
+        use crate::packet::attribute::PathAttribute;
+
+        let mut best = candidate("127.0.0.4", vec![as_path(&[64513])]);
+        best.route
+            .path_attributes
+            .push(PathAttribute::LocalPref(200));
+        let candidates = vec![
+            candidate("127.0.0.2", vec![as_path(&[64513, 64514])]),
+            best,
+            candidate("127.0.0.3", vec![as_path(&[64513])]),
+        ];
+
+        let (index, explanation) = select(&candidates, &Options::default()).unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(
+            explanation.losers,
+            vec![
+                ("127.0.0.2".parse().unwrap(), Reason::LocalPref),
+                ("127.0.0.3".parse().unwrap(), Reason::LocalPref),
+            ]
+        );
+        assert_eq!(
+            explanation.to_string(),
+            "best path from 127.0.0.4, 127.0.0.2 lost: lower LOCAL_PREF, \
+             127.0.0.3 lost: lower LOCAL_PREF"
+        );
+        assert!(select(&[], &Options::default()).is_none());
+    }
+
+    #[test]
+    fn select_does_not_depend_on_order() {
+        use crate::packet::attribute::PathAttribute;
+
+        // pairwise, 127.0.0.4 beats 127.0.0.2 on MED, 127.0.0.3 beats 127.0.0.4 and
+        // 127.0.0.2 beats 127.0.0.3 on peer address
+        let mut a = candidate("127.0.0.2", vec![as_path(&[64513])]);
+        a.route
+            .path_attributes
+            .push(PathAttribute::MultiExitDisc(20));
+        let mut b = candidate("127.0.0.4", vec![as_path(&[64513])]);
+        b.route
+            .path_attributes
+            .push(PathAttribute::MultiExitDisc(10));
+        b.received = a.received;
+        let mut c = candidate("127.0.0.3", vec![as_path(&[64514])]);
+        c.received = a.received;
+
+        for order in [
+            [&a, &b, &c],
+            [&a, &c, &b],
+            [&b, &a, &c],
+            [&b, &c, &a],
+            [&c, &a, &b],
+            [&c, &b, &a],
+        ] {
+            let candidates: Vec<_> = order.into_iter().cloned().collect();
+            let (index, explanation) = select(&candidates, &Options::default()).unwrap();
+            assert_eq!(candidates[index], c);
+            let mut losers = explanation.losers;
+            losers.sort();
+            assert_eq!(
+                losers,
+                vec![
+                    ("127.0.0.2".parse().unwrap(), Reason::Med),
+                    ("127.0.0.4".parse().unwrap(), Reason::PeerAddress),
+                ]
+            );
+        }
+    }
+
+    #[derive(Debug)]
+    struct CostByNextHop(std::collections::HashMap<crate::packet::attribute::NextHop, u32>);
+
+    impl NextHopCost for CostByNextHop {
+        fn cost(&self, next_hop: &crate::packet::attribute::NextHop) -> u32 {
+            self.0.get(next_hop).copied().unwrap_or(u32::MAX)
+        }
+    }
+
+    #[test]
+    fn igp_cost_is_compared_after_peer_type() {
+        let near = crate::packet::attribute::NextHop::V4("192.0.2.2".parse().unwrap());
+        let a = candidate("127.0.0.2", vec![as_path(&[64513])]);
+        let mut b = candidate("127.0.0.3", vec![as_path(&[64513])]);
+        b.route.next_hop = near;
+        b.received = a.received;
+
+        // every next hop costs the same by default
+        assert_eq!(reason(&a, &b), Reason::PeerAddress);
+
+        let options = Options {
+            next_hop_cost: Some(std::sync::Arc::new(CostByNextHop(
+                [(near, 10)].into_iter().collect(),
+            ))),
+            ..Default::default()
+        };
+        assert_eq!(
+            compare(&b, &a, &options),
+            Some((std::cmp::Ordering::Less, Reason::IgpCost))
+        );
+        let (index, explanation) = select(&[a, b], &options).unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(
+            explanation.to_string(),
+            "best path from 127.0.0.3, 127.0.0.2 lost: higher IGP cost to next hop"
+        );
+
+        // an iBGP route is not preferred for a cheaper next hop
+        let mut ibgp = candidate("127.0.0.3", vec![as_path(&[64513])]);
+        ibgp.route.next_hop = near;
+        ibgp.source.ebgp = false;
+        let ebgp = candidate("127.0.0.2", vec![as_path(&[64513])]);
+        assert_eq!(
+            compare(&ebgp, &ibgp, &options),
+            Some((std::cmp::Ordering::Less, Reason::PeerType))
+        );
+    }
+}
@@ -12,16 +12,62 @@ pub struct ConnectionErr {
     src: anyhow::Error,
 }
 
+#[derive(thiserror::Error, Debug)]
+pub enum CodecErr {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Message(#[from] ConvertBytesErr),
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("{src}")]
+pub struct ConvertBytesErr {
+    src: anyhow::Error,
+    notification: crate::packet::notification::NotificationMessage,
+    // a malformed NOTIFICATION is never answered with another one (RFC 4271 6.4)
+    in_notification: bool,
+}
+
+impl ConvertBytesErr {
+    pub fn new(
+        error_code: crate::packet::notification::ErrorCode,
+        data: &[u8],
+        src: anyhow::Error,
+    ) -> Self {
+        Self {
+            src,
+            notification: crate::packet::notification::NotificationMessage::new(error_code, data),
+            in_notification: false,
+        }
+    }
+
+    pub fn received_in_notification(self) -> Self {
+        Self {
+            in_notification: true,
+            ..self
+        }
+    }
+
+    pub fn is_in_notification(&self) -> bool {
+        self.in_notification
+    }
+
+    pub fn notification(&self) -> &crate::packet::notification::NotificationMessage {
+        &self.notification
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 #[error(transparent)]
-pub struct ConvertMessageErr {
+pub struct SpeakerErr {
     #[from]
     src: anyhow::Error,
 }
 
 #[derive(thiserror::Error, Debug)]
 #[error(transparent)]
-pub struct ConvertBytesErr {
+pub struct FibErr {
     #[from]
     src: anyhow::Error,
 }
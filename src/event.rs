@@ -1,6 +1,105 @@
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub enum Event {
     Start,
+    Stop,
+    ConnectRetryTimerExpires,
+    HoldTimerExpires,
+    KeepaliveTimerExpires,
+    IdleHoldTimerExpires,
     TcpConnect,
+    TcpConnectionConfirmed,
+    TcpConnectionFails,
     BgpOpen(crate::packet::open::OpenMessage),
+    BgpHeaderErr(crate::packet::notification::NotificationMessage),
+    BgpOpenMsgErr(crate::packet::notification::NotificationMessage),
+    NotifMsg(crate::packet::notification::NotificationMessage),
+    NotifMsgErr,
+    KeepAliveMsg,
+    UpdateMsg(crate::packet::update::UpdateMessage),
+    UpdateMsgErr(crate::packet::notification::NotificationMessage),
+    LocRibChanged,
+    CollisionOpen(crate::packet::open::OpenMessage),
+    CollisionFails,
+}
+
+impl From<crate::packet::message::Message> for Event {
+    fn from(message: crate::packet::message::Message) -> Self {
+        match message {
+            crate::packet::message::Message::Open(open) => Self::BgpOpen(open),
+            crate::packet::message::Message::Update(update) => Self::UpdateMsg(update),
+            crate::packet::message::Message::Notification(notification) => {
+                Self::NotifMsg(notification)
+            }
+            crate::packet::message::Message::KeepAlive(_) => Self::KeepAliveMsg,
+        }
+    }
+}
+
+impl From<crate::error::ConvertBytesErr> for Event {
+    fn from(err: crate::error::ConvertBytesErr) -> Self {
+        if err.is_in_notification() {
+            return Self::NotifMsgErr;
+        }
+        let notification = err.notification().clone();
+        match notification.error_code() {
+            crate::packet::notification::ErrorCode::OpenMessage(_) => {
+                Self::BgpOpenMsgErr(notification)
+            }
+            crate::packet::notification::ErrorCode::UpdateMessage(_) => {
+                Self::UpdateMsgErr(notification)
+            }
+            _ => Self::BgpHeaderErr(notification),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_from_message() {
+        let open = crate::packet::open::OpenMessage::new(
+            64512.into(),
+            "127.0.0.1".parse().unwrap(),
+            crate::types::HoldTime::new(),
+            vec![],
+        );
+        assert_eq!(
+            Event::from(crate::packet::message::Message::Open(open.clone())),
+            Event::BgpOpen(open)
+        );
+        assert_eq!(
+            Event::from(crate::packet::message::Message::new_keepalive()),
+            Event::KeepAliveMsg
+        );
+    }
+
+    #[test]
+    fn event_from_error() {
+        let mut invalid = bytes::BytesMut::new();
+        invalid.extend_from_slice(&[0xff; 16]);
+        invalid.extend_from_slice(&19u16.to_be_bytes());
+        invalid.extend_from_slice(&[5]);
+        let err = crate::packet::message::Message::try_from(invalid).unwrap_err();
+        assert!(matches!(Event::from(err), Event::BgpHeaderErr(_)));
+
+        let mut invalid: bytes::BytesMut = crate::packet::open::OpenMessage::new(
+            64512.into(),
+            "127.0.0.1".parse().unwrap(),
+            crate::types::HoldTime::new(),
+            vec![],
+        )
+        .into();
+        invalid[crate::constants::HEADER_LEN] = 3;
+        let err = crate::packet::message::Message::try_from(invalid).unwrap_err();
+        assert!(matches!(Event::from(err), Event::BgpOpenMsgErr(_)));
+
+        let mut invalid = bytes::BytesMut::new();
+        invalid.extend_from_slice(&[0xff; 16]);
+        invalid.extend_from_slice(&20u16.to_be_bytes());
+        invalid.extend_from_slice(&[3, 6]);
+        let err = crate::packet::message::Message::try_from(invalid).unwrap_err();
+        assert_eq!(Event::from(err), Event::NotifMsgErr);
+    }
 }
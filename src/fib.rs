@@ -0,0 +1,376 @@
+use futures::TryStreamExt as _;
+
+// the kernel (or any other) forwarding table best routes are installed into
+pub trait Fib {
+    fn install(
+        &mut self,
+        prefix: crate::packet::prefix::Prefix,
+        gateway: std::net::IpAddr,
+    ) -> impl std::future::Future<Output = Result<(), crate::error::FibErr>> + Send;
+
+    fn remove(
+        &mut self,
+        prefix: crate::packet::prefix::Prefix,
+    ) -> impl std::future::Future<Output = Result<(), crate::error::FibErr>> + Send;
+
+    // removes routes left behind by a previous run
+    fn flush(
+        &mut self,
+    ) -> impl std::future::Future<Output = Result<(), crate::error::FibErr>> + Send;
+}
+
+// routes in the given table tagged with the given protocol number belong to ekkyo
+#[derive(Debug, Clone)]
+pub struct NetlinkFib {
+    handle: rtnetlink::Handle,
+    table: u32,
+    protocol: u8,
+}
+
+impl NetlinkFib {
+    // spawns the netlink connection, so it has to be called within a tokio runtime
+    pub fn new(table: u32, protocol: u8) -> Result<Self, crate::error::FibErr> {
+        let (connection, handle, _) = rtnetlink::new_connection()
+            .map_err(|e| crate::error::FibErr::from(anyhow::Error::from(e)))?;
+        tokio::spawn(connection);
+        Ok(Self {
+            handle,
+            table,
+            protocol,
+        })
+    }
+
+    fn owns(&self, route: &netlink_packet_route::RouteMessage) -> bool {
+        // tables above 255 only fit in the RTA_TABLE attribute
+        let table = route
+            .nlas
+            .iter()
+            .find_map(|nla| match nla {
+                netlink_packet_route::route::Nla::Table(table) => Some(*table),
+                _ => None,
+            })
+            .unwrap_or(route.header.table.into());
+        route.header.protocol == self.protocol && table == self.table
+    }
+}
+
+impl Fib for NetlinkFib {
+    async fn install(
+        &mut self,
+        prefix: crate::packet::prefix::Prefix,
+        gateway: std::net::IpAddr,
+    ) -> Result<(), crate::error::FibErr> {
+        let request = self.handle.route().add();
+        match (prefix, gateway) {
+            (crate::packet::prefix::Prefix::V4(prefix), std::net::IpAddr::V4(gateway)) => {
+                request
+                    .v4()
+                    .destination_prefix(prefix.addr(), prefix.prefix_len())
+                    .gateway(gateway)
+                    .table_id(self.table)
+                    .protocol(self.protocol)
+                    .replace()
+                    .execute()
+                    .await
+            }
+            (crate::packet::prefix::Prefix::V6(prefix), std::net::IpAddr::V6(gateway)) => {
+                request
+                    .v6()
+                    .destination_prefix(prefix.addr(), prefix.prefix_len())
+                    .gateway(gateway)
+                    .table_id(self.table)
+                    .protocol(self.protocol)
+                    .replace()
+                    .execute()
+                    .await
+            }
+            _ => {
+                return Err(crate::error::FibErr::from(anyhow::anyhow!(
+                    "gateway {gateway} does not belong to the address family of {prefix}"
+                )))
+            }
+        }
+        .map_err(|e| crate::error::FibErr::from(anyhow::Error::from(e)))
+    }
+
+    async fn remove(
+        &mut self,
+        prefix: crate::packet::prefix::Prefix,
+    ) -> Result<(), crate::error::FibErr> {
+        let (address_family, destination, prefix_len) = match prefix {
+            crate::packet::prefix::Prefix::V4(prefix) => (
+                netlink_packet_route::AF_INET,
+                prefix.addr().octets().to_vec(),
+                prefix.prefix_len(),
+            ),
+            crate::packet::prefix::Prefix::V6(prefix) => (
+                netlink_packet_route::AF_INET6,
+                prefix.addr().octets().to_vec(),
+                prefix.prefix_len(),
+            ),
+        };
+        let mut message = netlink_packet_route::RouteMessage::default();
+        message.header.address_family = address_family as u8;
+        message.header.destination_prefix_length = prefix_len;
+        // the table is only given as an attribute since it may not fit in the header
+        message.header.table = netlink_packet_route::RT_TABLE_UNSPEC;
+        message.header.protocol = self.protocol;
+        message.header.scope = netlink_packet_route::RT_SCOPE_NOWHERE;
+        message.header.kind = netlink_packet_route::RTN_UNICAST;
+        message.nlas.extend([
+            netlink_packet_route::route::Nla::Destination(destination),
+            netlink_packet_route::route::Nla::Table(self.table),
+        ]);
+        self.handle
+            .route()
+            .del(message)
+            .execute()
+            .await
+            .map_err(|e| crate::error::FibErr::from(anyhow::Error::from(e)))
+    }
+
+    async fn flush(&mut self) -> Result<(), crate::error::FibErr> {
+        for ip_version in [rtnetlink::IpVersion::V4, rtnetlink::IpVersion::V6] {
+            let routes: Vec<_> = self
+                .handle
+                .route()
+                .get(ip_version)
+                .execute()
+                .try_collect()
+                .await
+                .map_err(|e| crate::error::FibErr::from(anyhow::Error::from(e)))?;
+            for route in routes.into_iter().filter(|route| self.owns(route)) {
+                tracing::info!("removing stale route: {:?}", route.header);
+                self.handle
+                    .route()
+                    .del(route)
+                    .execute()
+                    .await
+                    .map_err(|e| crate::error::FibErr::from(anyhow::Error::from(e)))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// keeps routes in memory so that FIB updates can be checked without privileges
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MockFib {
+    pub routes: std::collections::HashMap<crate::packet::prefix::Prefix, std::net::IpAddr>,
+}
+
+impl MockFib {
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl Fib for MockFib {
+    async fn install(
+        &mut self,
+        prefix: crate::packet::prefix::Prefix,
+        gateway: std::net::IpAddr,
+    ) -> Result<(), crate::error::FibErr> {
+        self.routes.insert(prefix, gateway);
+        Ok(())
+    }
+
+    async fn remove(
+        &mut self,
+        prefix: crate::packet::prefix::Prefix,
+    ) -> Result<(), crate::error::FibErr> {
+        self.routes
+            .remove(&prefix)
+            .map(|_| ())
+            .ok_or_else(|| crate::error::FibErr::from(anyhow::anyhow!("no route to {prefix}")))
+    }
+
+    async fn flush(&mut self) -> Result<(), crate::error::FibErr> {
+        self.routes.clear();
+        Ok(())
+    }
+}
+
+// mirrors the best routes of the Loc-RIB into a FIB
+#[derive(Debug)]
+pub struct FibSync<F> {
+    fib: F,
+    loc_rib: crate::rib::SharedLocRib,
+    installed: std::collections::HashMap<crate::packet::prefix::Prefix, std::net::IpAddr>,
+}
+
+impl<F: Fib> FibSync<F> {
+    pub fn new(fib: F, loc_rib: crate::rib::SharedLocRib) -> Self {
+        Self {
+            fib,
+            loc_rib,
+            installed: std::collections::HashMap::new(),
+        }
+    }
+
+    pub fn fib(&self) -> &F {
+        &self.fib
+    }
+
+    pub async fn run(mut self) -> Result<(), crate::error::FibErr> {
+        self.flush().await?;
+        let mut changes = self.loc_rib.lock().unwrap().subscribe();
+        loop {
+            self.sync().await;
+            if changes.changed().await.is_err() {
+                return Ok(());
+            }
+        }
+    }
+
+    pub async fn flush(&mut self) -> Result<(), crate::error::FibErr> {
+        self.installed.clear();
+        self.fib.flush().await
+    }
+
+    // failed updates are retried on the next sync
+    pub async fn sync(&mut self) {
+        let routes: std::collections::HashMap<_, _> = {
+            let loc_rib = self.loc_rib.lock().unwrap();
+            loc_rib
+                .best_candidates()
+                .filter(|candidate| !candidate.source.is_local())
+                .filter_map(|candidate| {
+                    Some((candidate.route.prefix, gateway(&candidate.route.next_hop)?))
+                })
+                .collect()
+        };
+
+        let removed: Vec<_> = self
+            .installed
+            .keys()
+            .filter(|prefix| !routes.contains_key(prefix))
+            .copied()
+            .collect();
+        for prefix in removed {
+            match self.fib.remove(prefix).await {
+                Ok(()) => {
+                    self.installed.remove(&prefix);
+                }
+                Err(e) => tracing::warn!("failed to remove route to {prefix}: {e}"),
+            }
+        }
+
+        for (prefix, gateway) in routes {
+            if self.installed.get(&prefix) == Some(&gateway) {
+                continue;
+            }
+            match self.fib.install(prefix, gateway).await {
+                Ok(()) => {
+                    self.installed.insert(prefix, gateway);
+                }
+                Err(e) => tracing::warn!("failed to install route to {prefix}: {e}"),
+            }
+        }
+    }
+}
+
+// IPv4-mapped IPv6 next hops cannot be used as a kernel gateway
+fn gateway(next_hop: &crate::packet::attribute::NextHop) -> Option<std::net::IpAddr> {
+    match next_hop {
+        crate::packet::attribute::NextHop::V4(addr) => Some((*addr).into()),
+        crate::packet::attribute::NextHop::V6 { global, .. } => match global.to_ipv4_mapped() {
+            Some(_) => None,
+            None => Some((*global).into()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(
+        address: &str,
+        prefix: &str,
+        next_hop: crate::packet::attribute::NextHop,
+    ) -> crate::rib::Candidate {
+        crate::rib::Candidate::new(
+            crate::rib::Source {
+                address: address.parse().unwrap(),
+                router_id: "10.0.0.2".parse().unwrap(),
+                ebgp: true,
+            },
+            crate::route::Route {
+                next_hop,
+                ..crate::route::Route::local(prefix.parse().unwrap())
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn fib_follows_best_routes() {
+        let loc_rib = crate::rib::LocRib::shared();
+        let v4_next_hop = crate::packet::attribute::NextHop::V4("10.200.100.3".parse().unwrap());
+        let v6_next_hop = crate::packet::attribute::NextHop::V6 {
+            global: "2001:db8::3".parse().unwrap(),
+            link_local: None,
+        };
+        {
+            let mut loc_rib = loc_rib.lock().unwrap();
+            loc_rib.insert(candidate("10.200.100.3", "10.100.220.0/24", v4_next_hop));
+            loc_rib.insert(candidate("10.200.100.3", "2001:db8:1::/48", v6_next_hop));
+            loc_rib.insert(crate::rib::Candidate::new(
+                crate::rib::Source::local("10.0.0.1".parse().unwrap()),
+                crate::route::Route::local("10.100.210.0/24".parse().unwrap()),
+            ));
+        }
+
+        let mut fib_sync = FibSync::new(MockFib::new(), loc_rib.clone());
+        fib_sync.sync().await;
+        // locally originated routes are not installed
+        assert_eq!(
+            fib_sync.fib().routes,
+            std::collections::HashMap::from([
+                (
+                    "10.100.220.0/24".parse().unwrap(),
+                    "10.200.100.3".parse().unwrap()
+                ),
+                (
+                    "2001:db8:1::/48".parse().unwrap(),
+                    "2001:db8::3".parse().unwrap()
+                ),
+            ])
+        );
+
+        loc_rib
+            .lock()
+            .unwrap()
+            .remove_peer("10.200.100.3".parse().unwrap());
+        fib_sync.sync().await;
+        assert!(fib_sync.fib().routes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn stale_routes_are_flushed() {
+        let mut fib = MockFib::new();
+        fib.install(
+            "10.100.220.0/24".parse().unwrap(),
+            "10.200.100.3".parse().unwrap(),
+        )
+        .await
+        .unwrap();
+
+        let loc_rib = crate::rib::LocRib::shared();
+        loc_rib.lock().unwrap().insert(candidate(
+            "10.200.100.3",
+            "10.100.230.0/24",
+            crate::packet::attribute::NextHop::V4("10.200.100.3".parse().unwrap()),
+        ));
+        let mut fib_sync = FibSync::new(fib, loc_rib);
+        fib_sync.flush().await.unwrap();
+        fib_sync.sync().await;
+        assert_eq!(
+            fib_sync.fib().routes,
+            std::collections::HashMap::from([(
+                "10.100.230.0/24".parse().unwrap(),
+                "10.200.100.3".parse().unwrap()
+            )])
+        );
+    }
+}
@@ -1,10 +1,19 @@
+mod clock;
 pub mod config;
 mod connection;
 pub mod constants;
-mod error;
+pub mod decision;
+pub mod error;
 mod event;
-mod packet;
+pub mod fib;
+pub mod listener;
+pub mod packet;
 pub mod peer;
 mod queue;
-mod state;
-mod types;
+pub mod rib;
+pub mod route;
+pub mod speaker;
+pub mod state;
+mod timer;
+pub mod transport;
+pub mod types;
@@ -0,0 +1,175 @@
+use anyhow::Context as _;
+
+type Inbound = tokio::sync::mpsc::UnboundedSender<tokio::net::TcpStream>;
+
+// accepts connections on a local address and hands each one to the peer
+// configured with the remote address it comes from
+#[derive(Debug, Clone)]
+pub struct Listener {
+    listener: std::sync::Arc<tokio::net::TcpListener>,
+    peers: std::sync::Arc<std::sync::Mutex<std::collections::HashMap<std::net::IpAddr, Inbound>>>,
+}
+
+impl Listener {
+    pub async fn bind(local: std::net::SocketAddr) -> Result<Self, crate::error::ConnectionErr> {
+        let listener = tokio::net::TcpListener::bind(local)
+            .await
+            .context(format!("failed to bind to {local}"))?;
+        Ok(Self {
+            listener: std::sync::Arc::new(listener),
+            peers: Default::default(),
+        })
+    }
+
+    pub fn local_addr(&self) -> std::io::Result<std::net::SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub async fn run(&self) {
+        loop {
+            match self.listener.accept().await {
+                Ok((stream, remote)) => self.dispatch(stream, remote),
+                Err(e) => tracing::warn!("failed to accept connection: {:?}", e),
+            }
+        }
+    }
+
+    fn dispatch(&self, stream: tokio::net::TcpStream, remote: std::net::SocketAddr) {
+        let remote_ip = remote.ip().to_canonical();
+        let mut peers = self.peers.lock().unwrap();
+        let Some(peer) = peers.get(&remote_ip) else {
+            tracing::warn!("rejected connection from unknown peer {remote}");
+            return;
+        };
+        if peer.send(stream).is_err() {
+            tracing::warn!("rejected connection from {remote} as the peer is no longer running");
+            peers.remove(&remote_ip);
+        } else {
+            tracing::info!("accepted connection from {remote}");
+        }
+    }
+}
+
+impl crate::transport::Acceptor for Listener {
+    type Transport = tokio::net::TcpStream;
+
+    async fn run(&self) {
+        Listener::run(self).await
+    }
+
+    fn register(
+        &self,
+        remote_ip: std::net::IpAddr,
+    ) -> tokio::sync::mpsc::UnboundedReceiver<tokio::net::TcpStream> {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        self.peers
+            .lock()
+            .unwrap()
+            .insert(remote_ip.to_canonical(), tx);
+        rx
+    }
+
+    fn unregister(&self, remote_ip: std::net::IpAddr) {
+        self.peers.lock().unwrap().remove(&remote_ip.to_canonical());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::Acceptor as _;
+    use tokio::io::AsyncReadExt as _;
+
+    async fn connect_from(local_ip: &str, remote: std::net::SocketAddr) -> tokio::net::TcpStream {
+        let socket = tokio::net::TcpSocket::new_v4().unwrap();
+        socket
+            .bind((local_ip.parse::<std::net::IpAddr>().unwrap(), 0).into())
+            .unwrap();
+        socket.connect(remote).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn connections_are_dispatched_by_remote_address() {
+        let listener = Listener::bind("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        let local = listener.local_addr().unwrap();
+        let mut peer1 = listener.register("127.0.0.11".parse().unwrap());
+        let mut peer2 = listener.register("127.0.0.12".parse().unwrap());
+        tokio::spawn({
+            let listener = listener.clone();
+            async move { listener.run().await }
+        });
+
+        let _stream = connect_from("127.0.0.12", local).await;
+        let accepted = peer2.recv().await.unwrap();
+        assert_eq!(
+            accepted.peer_addr().unwrap().ip(),
+            "127.0.0.12".parse::<std::net::IpAddr>().unwrap()
+        );
+        assert!(peer1.try_recv().is_err());
+
+        // unknown sources are disconnected right away
+        let mut stream = connect_from("127.0.0.13", local).await;
+        assert_eq!(stream.read(&mut [0; 1]).await.unwrap(), 0);
+
+        listener.unregister("127.0.0.11".parse().unwrap());
+        let mut stream = connect_from("127.0.0.11", local).await;
+        assert_eq!(stream.read(&mut [0; 1]).await.unwrap(), 0);
+        assert!(peer1.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn ipv6_connections_are_dispatched() {
+        use crate::transport::Connector as _;
+        use std::str::FromStr as _;
+
+        let listener = Listener::bind("[::1]:0".parse().unwrap()).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mut peer = listener.register("::1".parse().unwrap());
+        tokio::spawn({
+            let listener = listener.clone();
+            async move { listener.run().await }
+        });
+
+        let config = crate::config::Config::from_str(&format!(
+            "64512 ::1 64513 ::1 active 90 120 10.0.0.1 remote-port {port} update-source ::1"
+        ))
+        .unwrap();
+        let stream = crate::transport::TcpConnector
+            .connect(&config)
+            .await
+            .unwrap();
+        assert_eq!(
+            stream.local_addr().unwrap().ip(),
+            "::1".parse::<std::net::IpAddr>().unwrap()
+        );
+        let accepted = tokio::time::timeout(std::time::Duration::from_secs(5), peer.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(accepted.peer_addr().unwrap(), stream.local_addr().unwrap());
+    }
+
+    #[tokio::test]
+    async fn ipv4_mapped_connections_are_dispatched_by_ipv4_address() {
+        // a dual-stack listener sees IPv4 peers as IPv4-mapped IPv6 addresses
+        let listener = Listener::bind("[::]:0".parse().unwrap()).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mut peer = listener.register("127.0.0.12".parse().unwrap());
+        tokio::spawn({
+            let listener = listener.clone();
+            async move { listener.run().await }
+        });
+
+        let _stream = connect_from("127.0.0.12", ([127, 0, 0, 1], port).into()).await;
+        let accepted = tokio::time::timeout(std::time::Duration::from_secs(5), peer.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            accepted.peer_addr().unwrap().ip().to_canonical(),
+            "127.0.0.12".parse::<std::net::IpAddr>().unwrap()
+        );
+    }
+}
@@ -1,40 +1,47 @@
-use std::str::FromStr as _;
+use futures::StreamExt as _;
 
 #[tokio::main]
 async fn main() {
     tracing_subscriber::fmt::init();
 
-    let config = std::env::args().skip(1).fold("".to_owned(), |mut acc, s| {
-        acc += &(s.to_owned() + " ");
-        acc
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    // `--config <file>` reads a TOML file, otherwise the arguments describe a single peer
+    let config = match args.as_slice() {
+        [flag, path] if flag == "--config" => ekkyo::config::SpeakerConfig::from_file(path),
+        [] => Ok(ekkyo::config::Config::default().into()),
+        _ => ekkyo::config::SpeakerConfig::from_args(&args.join(" ")),
+    }
+    .unwrap_or_else(|e| {
+        eprintln!("{e:#}");
+        std::process::exit(1);
     });
 
-    let mut configs = vec![ekkyo::config::Config::from_str(config.trim_end()).unwrap()];
-
-    if config.is_empty() {
-        configs = vec![ekkyo::config::Config::default()];
+    let speaker = ekkyo::speaker::Speaker::new(config.router_id);
+    // networks are originated once for the whole speaker, not per neighbor
+    for network in &config.networks {
+        speaker.inject(ekkyo::route::Route::local(*network));
     }
-
-    let mut peers: Vec<ekkyo::peer::Peer> =
-        configs.into_iter().map(ekkyo::peer::Peer::new).collect();
-
-    for peer in &mut peers {
-        peer.start();
+    match ekkyo::fib::NetlinkFib::new(config.fib_table, config.fib_protocol) {
+        Ok(fib) => {
+            let fib_sync = ekkyo::fib::FibSync::new(fib, speaker.loc_rib());
+            tokio::spawn(async move {
+                if let Err(e) = fib_sync.run().await {
+                    tracing::warn!("failed to update FIB: {e}");
+                }
+            });
+        }
+        Err(e) => tracing::warn!("FIB is not available: {e}"),
     }
 
-    let mut handles = Vec::new();
-
-    for mut peer in peers {
-        let handle = tokio::spawn(async move {
-            loop {
-                peer.next().await;
-                tokio::time::sleep(tokio::time::Duration::from_secs_f32(0.1)).await;
-            }
-        });
-        handles.push(handle);
+    let mut events = Box::pin(speaker.subscribe());
+    for neighbor in config.neighbors {
+        if let Err(e) = speaker.add_neighbor(neighbor).await {
+            eprintln!("{e:#}");
+            std::process::exit(1);
+        }
     }
-
-    for handle in handles {
-        handle.await.unwrap();
+    // the speaker keeps running as long as it is referenced here
+    while let Some(event) = events.next().await {
+        tracing::info!("{event:?}");
     }
 }
@@ -0,0 +1,1139 @@
+use bytes::BufMut as _;
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Default)]
+pub struct AttributeFlags(u8);
+
+impl AttributeFlags {
+    const OPTIONAL: u8 = 0x80;
+    const TRANSITIVE: u8 = 0x40;
+    const PARTIAL: u8 = 0x20;
+    const EXTENDED_LENGTH: u8 = 0x10;
+
+    pub fn new(optional: bool, transitive: bool) -> Self {
+        let mut flags = Self::default();
+        flags.set(Self::OPTIONAL, optional);
+        flags.set(Self::TRANSITIVE, transitive);
+        flags
+    }
+
+    pub fn optional(&self) -> bool {
+        self.0 & Self::OPTIONAL != 0
+    }
+
+    pub fn transitive(&self) -> bool {
+        self.0 & Self::TRANSITIVE != 0
+    }
+
+    pub fn partial(&self) -> bool {
+        self.0 & Self::PARTIAL != 0
+    }
+
+    pub fn extended_length(&self) -> bool {
+        self.0 & Self::EXTENDED_LENGTH != 0
+    }
+
+    pub fn with_partial(mut self, partial: bool) -> Self {
+        self.set(Self::PARTIAL, partial);
+        self
+    }
+
+    pub fn with_extended_length(mut self, extended_length: bool) -> Self {
+        self.set(Self::EXTENDED_LENGTH, extended_length);
+        self
+    }
+
+    fn set(&mut self, flag: u8, value: bool) {
+        if value {
+            self.0 |= flag;
+        } else {
+            self.0 &= !flag;
+        }
+    }
+}
+
+impl From<u8> for AttributeFlags {
+    fn from(flags: u8) -> Self {
+        // the lower four bits are unused and must be zero
+        Self(flags & 0xf0)
+    }
+}
+
+impl From<AttributeFlags> for u8 {
+    fn from(flags: AttributeFlags) -> u8 {
+        flags.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Origin {
+    Igp,
+    Egp,
+    Incomplete,
+}
+
+impl TryFrom<u8> for Origin {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Igp),
+            1 => Ok(Self::Egp),
+            2 => Ok(Self::Incomplete),
+            _ => Err(anyhow::anyhow!("invalid origin: {value}")),
+        }
+    }
+}
+
+impl From<Origin> for u8 {
+    fn from(origin: Origin) -> u8 {
+        match origin {
+            Origin::Igp => 0,
+            Origin::Egp => 1,
+            Origin::Incomplete => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum AsPathSegment {
+    AsSet(Vec<crate::types::ASNum>),
+    AsSequence(Vec<crate::types::ASNum>),
+}
+
+impl AsPathSegment {
+    const AS_SET: u8 = 1;
+    const AS_SEQUENCE: u8 = 2;
+
+    pub fn asns(&self) -> &[crate::types::ASNum] {
+        match self {
+            Self::AsSet(asns) | Self::AsSequence(asns) => asns,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Default)]
+pub struct AsPath(Vec<AsPathSegment>);
+
+impl AsPath {
+    pub fn new(segments: Vec<AsPathSegment>) -> Self {
+        Self(segments)
+    }
+
+    pub fn segments(&self) -> &[AsPathSegment] {
+        &self.0
+    }
+
+    // an AS_SET counts as a single AS regardless of its size
+    pub fn path_len(&self) -> usize {
+        self.0
+            .iter()
+            .map(|segment| match segment {
+                AsPathSegment::AsSet(_) => 1,
+                AsPathSegment::AsSequence(asns) => asns.len(),
+            })
+            .sum()
+    }
+
+    pub fn contains(&self, asn: crate::types::ASNum) -> bool {
+        self.0.iter().any(|segment| segment.asns().contains(&asn))
+    }
+
+    // adds the AS to the leading AS_SEQUENCE, starting a new one when there is
+    // none or it is full
+    pub fn prepend(&mut self, asn: crate::types::ASNum) {
+        match self.0.first_mut() {
+            Some(AsPathSegment::AsSequence(asns)) if asns.len() < u8::MAX as usize => {
+                asns.insert(0, asn)
+            }
+            _ => self.0.insert(0, AsPathSegment::AsSequence(vec![asn])),
+        }
+    }
+
+    fn has_four_octet(&self) -> bool {
+        self.0
+            .iter()
+            .any(|segment| segment.asns().iter().any(|asn| asn.is_four_octet()))
+    }
+
+    // rebuilds the real path from an AS_PATH containing AS_TRANS and the AS4_PATH
+    // received from a two-octet speaker as described in RFC 6793 section 4.2.3
+    fn merge_as4_path(&self, as4_path: &AsPath) -> AsPath {
+        let Some(mut leading) = self.path_len().checked_sub(as4_path.path_len()) else {
+            return self.clone();
+        };
+
+        let mut segments = Vec::new();
+        for segment in &self.0 {
+            if leading == 0 {
+                break;
+            }
+            match segment {
+                AsPathSegment::AsSet(_) => {
+                    segments.push(segment.clone());
+                    leading -= 1;
+                }
+                AsPathSegment::AsSequence(asns) => {
+                    let len = std::cmp::min(leading, asns.len());
+                    segments.push(AsPathSegment::AsSequence(asns[..len].to_vec()));
+                    leading -= len;
+                }
+            }
+        }
+        segments.extend(as4_path.0.iter().cloned());
+
+        AsPath(segments)
+    }
+
+    fn encode(&self, bytes: &mut bytes::BytesMut, four_octet_as: bool) {
+        for segment in &self.0 {
+            let segment_type = match segment {
+                AsPathSegment::AsSet(_) => AsPathSegment::AS_SET,
+                AsPathSegment::AsSequence(_) => AsPathSegment::AS_SEQUENCE,
+            };
+            bytes.put_u8(segment_type);
+            bytes.put_u8(segment.asns().len() as u8);
+            for asn in segment.asns() {
+                if four_octet_as {
+                    bytes.put_u32((*asn).into());
+                } else {
+                    bytes.put_u16(asn.to_two_octet());
+                }
+            }
+        }
+    }
+
+    fn decode(value: &[u8], four_octet_as: bool) -> anyhow::Result<Self> {
+        let asn_len = if four_octet_as { 4 } else { 2 };
+        let mut segments = Vec::new();
+        let mut rest = value;
+        while !rest.is_empty() {
+            if rest.len() < 2 {
+                return Err(anyhow::anyhow!("truncated AS_PATH segment: {:?}", rest));
+            }
+
+            let segment_type = rest[0];
+            let count = rest[1] as usize;
+            if count == 0 || rest.len() < 2 + count * asn_len {
+                return Err(anyhow::anyhow!("invalid AS_PATH segment length: {count}"));
+            }
+
+            let asns = rest[2..2 + count * asn_len]
+                .chunks_exact(asn_len)
+                .map(|asn| {
+                    crate::types::ASNum::from(if four_octet_as {
+                        u32::from_be_bytes([asn[0], asn[1], asn[2], asn[3]])
+                    } else {
+                        u16::from_be_bytes([asn[0], asn[1]]) as u32
+                    })
+                })
+                .collect();
+            segments.push(match segment_type {
+                AsPathSegment::AS_SET => AsPathSegment::AsSet(asns),
+                AsPathSegment::AS_SEQUENCE => AsPathSegment::AsSequence(asns),
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "invalid AS_PATH segment type: {segment_type}"
+                    ))
+                }
+            });
+            rest = &rest[2 + count * asn_len..];
+        }
+
+        Ok(Self(segments))
+    }
+}
+
+// IPv6 next hops carry an optional link-local address after the global one (RFC 2545)
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NextHop {
+    V4(std::net::Ipv4Addr),
+    V6 {
+        global: std::net::Ipv6Addr,
+        link_local: Option<std::net::Ipv6Addr>,
+    },
+}
+
+impl NextHop {
+    pub fn is_unspecified(&self) -> bool {
+        match self {
+            Self::V4(addr) => addr.is_unspecified(),
+            Self::V6 { global, .. } => global.is_unspecified(),
+        }
+    }
+
+    fn encode(&self, bytes: &mut bytes::BytesMut) {
+        match self {
+            Self::V4(addr) => bytes.put_u32((*addr).into()),
+            Self::V6 { global, link_local } => {
+                bytes.put_u128((*global).into());
+                if let Some(link_local) = link_local {
+                    bytes.put_u128((*link_local).into());
+                }
+            }
+        }
+    }
+
+    fn encoded_len(&self) -> usize {
+        match self {
+            Self::V4(_) => 4,
+            Self::V6 { link_local, .. } => 16 + link_local.map_or(0, |_| 16),
+        }
+    }
+
+    fn decode(afi: crate::types::Afi, value: &[u8]) -> anyhow::Result<Self> {
+        let ipv6 = |octets: &[u8]| {
+            std::net::Ipv6Addr::from(<[u8; 16]>::try_from(octets).expect("16 octets"))
+        };
+        match (afi, value.len()) {
+            (crate::types::Afi::Ipv4, 4) => Ok(Self::V4(std::net::Ipv4Addr::new(
+                value[0], value[1], value[2], value[3],
+            ))),
+            (crate::types::Afi::Ipv6, 16) => Ok(Self::V6 {
+                global: ipv6(value),
+                link_local: None,
+            }),
+            (crate::types::Afi::Ipv6, 32) => Ok(Self::V6 {
+                global: ipv6(&value[..16]),
+                link_local: Some(ipv6(&value[16..])),
+            }),
+            (afi, len) => Err(anyhow::anyhow!(
+                "invalid next hop length for {:?}: {len}",
+                afi
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct MpReachNlri {
+    pub afi: crate::types::Afi,
+    pub safi: crate::types::Safi,
+    pub next_hop: NextHop,
+    pub nlri: Vec<crate::packet::prefix::Prefix>,
+}
+
+impl MpReachNlri {
+    fn encode(&self, bytes: &mut bytes::BytesMut) {
+        bytes.put_u16(self.afi.into());
+        bytes.put_u8(self.safi.into());
+        bytes.put_u8(self.next_hop.encoded_len() as u8);
+        self.next_hop.encode(bytes);
+        // reserved
+        bytes.put_u8(0);
+        for prefix in &self.nlri {
+            prefix.encode(bytes);
+        }
+    }
+
+    fn decode(value: &[u8]) -> anyhow::Result<Self> {
+        if value.len() < 5 {
+            return Err(anyhow::anyhow!("truncated MP_REACH_NLRI: {:?}", value));
+        }
+
+        let afi = crate::types::Afi::try_from(u16::from_be_bytes([value[0], value[1]]))?;
+        let safi = crate::types::Safi::try_from(value[2])?;
+        let next_hop_len = value[3] as usize;
+        if value.len() < 5 + next_hop_len {
+            return Err(anyhow::anyhow!("invalid next hop length: {next_hop_len}"));
+        }
+        let next_hop = NextHop::decode(afi, &value[4..4 + next_hop_len])?;
+        let nlri = crate::packet::prefix::Prefix::decode_all(afi, &value[5 + next_hop_len..])?;
+
+        Ok(Self {
+            afi,
+            safi,
+            next_hop,
+            nlri,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct MpUnreachNlri {
+    pub afi: crate::types::Afi,
+    pub safi: crate::types::Safi,
+    pub withdrawn_routes: Vec<crate::packet::prefix::Prefix>,
+}
+
+impl MpUnreachNlri {
+    fn encode(&self, bytes: &mut bytes::BytesMut) {
+        bytes.put_u16(self.afi.into());
+        bytes.put_u8(self.safi.into());
+        for prefix in &self.withdrawn_routes {
+            prefix.encode(bytes);
+        }
+    }
+
+    fn decode(value: &[u8]) -> anyhow::Result<Self> {
+        if value.len() < 3 {
+            return Err(anyhow::anyhow!("truncated MP_UNREACH_NLRI: {:?}", value));
+        }
+
+        let afi = crate::types::Afi::try_from(u16::from_be_bytes([value[0], value[1]]))?;
+        let safi = crate::types::Safi::try_from(value[2])?;
+        let withdrawn_routes = crate::packet::prefix::Prefix::decode_all(afi, &value[3..])?;
+
+        Ok(Self {
+            afi,
+            safi,
+            withdrawn_routes,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum PathAttribute {
+    Origin(Origin),
+    AsPath(AsPath),
+    NextHop(std::net::Ipv4Addr),
+    MultiExitDisc(u32),
+    LocalPref(u32),
+    AtomicAggregate,
+    // the Partial bit is kept as received so that it is re-advertised (RFC 4271 5)
+    Aggregator {
+        asn: crate::types::ASNum,
+        addr: std::net::Ipv4Addr,
+        partial: bool,
+    },
+    MpReachNlri(MpReachNlri),
+    MpUnreachNlri(MpUnreachNlri),
+    As4Path(AsPath),
+    As4Aggregator(crate::types::ASNum, std::net::Ipv4Addr),
+    Unknown {
+        flags: AttributeFlags,
+        type_code: u8,
+        value: bytes::BytesMut,
+    },
+}
+
+impl PathAttribute {
+    pub const ORIGIN: u8 = 1;
+    pub const AS_PATH: u8 = 2;
+    pub const NEXT_HOP: u8 = 3;
+    pub const MULTI_EXIT_DISC: u8 = 4;
+    pub const LOCAL_PREF: u8 = 5;
+    pub const ATOMIC_AGGREGATE: u8 = 6;
+    pub const AGGREGATOR: u8 = 7;
+    pub const MP_REACH_NLRI: u8 = 14;
+    pub const MP_UNREACH_NLRI: u8 = 15;
+    pub const AS4_PATH: u8 = 17;
+    pub const AS4_AGGREGATOR: u8 = 18;
+
+    pub fn type_code(&self) -> u8 {
+        match self {
+            Self::Origin(_) => Self::ORIGIN,
+            Self::AsPath(_) => Self::AS_PATH,
+            Self::NextHop(_) => Self::NEXT_HOP,
+            Self::MultiExitDisc(_) => Self::MULTI_EXIT_DISC,
+            Self::LocalPref(_) => Self::LOCAL_PREF,
+            Self::AtomicAggregate => Self::ATOMIC_AGGREGATE,
+            Self::Aggregator { .. } => Self::AGGREGATOR,
+            Self::MpReachNlri(_) => Self::MP_REACH_NLRI,
+            Self::MpUnreachNlri(_) => Self::MP_UNREACH_NLRI,
+            Self::As4Path(_) => Self::AS4_PATH,
+            Self::As4Aggregator(_, _) => Self::AS4_AGGREGATOR,
+            Self::Unknown { type_code, .. } => *type_code,
+        }
+    }
+
+    pub fn flags(&self) -> AttributeFlags {
+        match self {
+            Self::Unknown { flags, .. } => *flags,
+            Self::Aggregator { partial, .. } => {
+                AttributeFlags::new(true, true).with_partial(*partial)
+            }
+            _ => Self::expected_flags(self.type_code())
+                .expect("known attributes always have expected flags"),
+        }
+    }
+
+    // returns the (optional, transitive) flags mandated by RFC 4271 for known attributes
+    fn expected_flags(type_code: u8) -> Option<AttributeFlags> {
+        match type_code {
+            Self::ORIGIN
+            | Self::AS_PATH
+            | Self::NEXT_HOP
+            | Self::LOCAL_PREF
+            | Self::ATOMIC_AGGREGATE => Some(AttributeFlags::new(false, true)),
+            Self::MULTI_EXIT_DISC | Self::MP_REACH_NLRI | Self::MP_UNREACH_NLRI => {
+                Some(AttributeFlags::new(true, false))
+            }
+            Self::AGGREGATOR | Self::AS4_PATH | Self::AS4_AGGREGATOR => {
+                Some(AttributeFlags::new(true, true))
+            }
+            _ => None,
+        }
+    }
+
+    fn value(&self, four_octet_as: bool) -> bytes::BytesMut {
+        let mut value = bytes::BytesMut::new();
+        match self {
+            Self::Origin(origin) => value.put_u8((*origin).into()),
+            Self::AsPath(as_path) => as_path.encode(&mut value, four_octet_as),
+            Self::NextHop(next_hop) => value.put_u32((*next_hop).into()),
+            Self::MultiExitDisc(med) => value.put_u32(*med),
+            Self::LocalPref(local_pref) => value.put_u32(*local_pref),
+            Self::AtomicAggregate => {}
+            Self::Aggregator { asn, addr, .. } => {
+                if four_octet_as {
+                    value.put_u32((*asn).into());
+                } else {
+                    value.put_u16(asn.to_two_octet());
+                }
+                value.put_u32((*addr).into());
+            }
+            Self::MpReachNlri(mp_reach) => mp_reach.encode(&mut value),
+            Self::MpUnreachNlri(mp_unreach) => mp_unreach.encode(&mut value),
+            Self::As4Path(as_path) => as_path.encode(&mut value, true),
+            Self::As4Aggregator(asn, addr) => {
+                value.put_u32((*asn).into());
+                value.put_u32((*addr).into());
+            }
+            Self::Unknown { value: v, .. } => value.put(&v[..]),
+        }
+        value
+    }
+
+    pub fn encode(&self, bytes: &mut bytes::BytesMut, four_octet_as: bool) {
+        let value = self.value(four_octet_as);
+        let extended_length = value.len() > u8::MAX as usize;
+        bytes.put_u8(self.flags().with_extended_length(extended_length).into());
+        bytes.put_u8(self.type_code());
+        if extended_length {
+            bytes.put_u16(value.len() as u16);
+        } else {
+            bytes.put_u8(value.len() as u8);
+        }
+        bytes.put(value);
+    }
+
+    // AS4_PATH and AS4_AGGREGATOR are generated here for two-octet sessions
+    // instead of being carried in the attribute list
+    pub fn encode_all(attributes: &[Self], bytes: &mut bytes::BytesMut, four_octet_as: bool) {
+        let mut as4_attributes = Vec::new();
+        for attribute in attributes {
+            match attribute {
+                Self::As4Path(_) | Self::As4Aggregator(_, _) => continue,
+                Self::AsPath(as_path) if !four_octet_as && as_path.has_four_octet() => {
+                    as4_attributes.push(Self::As4Path(as_path.clone()))
+                }
+                Self::Aggregator { asn, addr, .. } if !four_octet_as && asn.is_four_octet() => {
+                    as4_attributes.push(Self::As4Aggregator(*asn, *addr))
+                }
+                _ => {}
+            }
+            attribute.encode(bytes, four_octet_as);
+        }
+        for attribute in as4_attributes {
+            attribute.encode(bytes, four_octet_as);
+        }
+    }
+
+    pub fn decode_all(
+        value: &[u8],
+        four_octet_as: bool,
+    ) -> Result<Vec<Self>, crate::error::ConvertBytesErr> {
+        let mut attributes: Vec<Self> = Vec::new();
+        let mut rest = value;
+        while !rest.is_empty() {
+            let (attribute, len) = Self::decode(rest, four_octet_as)?;
+            if let Some(attribute) = attribute {
+                if attributes
+                    .iter()
+                    .any(|a| a.type_code() == attribute.type_code())
+                {
+                    return Err(update_message_error(
+                        crate::packet::notification::UpdateMessageErrorSubcode::MalformedAttributeList,
+                        &[],
+                        anyhow::anyhow!("duplicate attribute: {}", attribute.type_code()),
+                    ));
+                }
+                attributes.push(attribute);
+            }
+            rest = &rest[len..];
+        }
+
+        Self::merge_as4_attributes(&mut attributes, four_octet_as);
+        Ok(attributes)
+    }
+
+    // folds AS4_PATH and AS4_AGGREGATOR into AS_PATH and AGGREGATOR as RFC 6793
+    // requires; four-octet speakers must not send them and they are discarded
+    fn merge_as4_attributes(attributes: &mut Vec<Self>, four_octet_as: bool) {
+        let as4_path = attributes.iter().find_map(|a| match a {
+            Self::As4Path(as_path) => Some(as_path.clone()),
+            _ => None,
+        });
+        let as4_aggregator = attributes.iter().find_map(|a| match a {
+            Self::As4Aggregator(asn, addr) => Some((*asn, *addr)),
+            _ => None,
+        });
+        attributes.retain(|a| !matches!(a, Self::As4Path(_) | Self::As4Aggregator(_, _)));
+        if four_octet_as {
+            return;
+        }
+
+        if let Some((as4, as4_addr)) = as4_aggregator {
+            for attribute in attributes.iter_mut() {
+                if let Self::Aggregator { asn, addr, .. } = attribute {
+                    // an AGGREGATOR not carrying AS_TRANS means that AS4_* came from elsewhere
+                    if *asn != crate::types::ASNum::AS_TRANS {
+                        return;
+                    }
+                    *asn = as4;
+                    *addr = as4_addr;
+                }
+            }
+        }
+        if let Some(as4_path) = as4_path {
+            for attribute in attributes.iter_mut() {
+                if let Self::AsPath(as_path) = attribute {
+                    *as_path = as_path.merge_as4_path(&as4_path);
+                }
+            }
+        }
+    }
+
+    // decodes a single attribute and returns it with the number of bytes consumed;
+    // unrecognized optional non-transitive attributes are quietly ignored
+    fn decode(
+        value: &[u8],
+        four_octet_as: bool,
+    ) -> Result<(Option<Self>, usize), crate::error::ConvertBytesErr> {
+        use crate::packet::notification::UpdateMessageErrorSubcode;
+
+        if value.len() < 3 {
+            return Err(update_message_error(
+                UpdateMessageErrorSubcode::MalformedAttributeList,
+                &[],
+                anyhow::anyhow!("truncated attribute header: {:?}", value),
+            ));
+        }
+
+        let flags = AttributeFlags::from(value[0]);
+        let type_code = value[1];
+        let (len, hdr_len) = if flags.extended_length() {
+            if value.len() < 4 {
+                return Err(update_message_error(
+                    UpdateMessageErrorSubcode::MalformedAttributeList,
+                    &[],
+                    anyhow::anyhow!("truncated attribute header: {:?}", value),
+                ));
+            }
+            (u16::from_be_bytes([value[2], value[3]]) as usize, 4)
+        } else {
+            (value[2] as usize, 3)
+        };
+        if value.len() < hdr_len + len {
+            return Err(update_message_error(
+                UpdateMessageErrorSubcode::AttributeLengthError,
+                &value[..hdr_len],
+                anyhow::anyhow!("attribute length exceeds attribute list: {len}"),
+            ));
+        }
+
+        let raw = &value[..hdr_len + len];
+        let body = &value[hdr_len..hdr_len + len];
+
+        let Some(expected) = Self::expected_flags(type_code) else {
+            if !flags.optional() {
+                return Err(update_message_error(
+                    UpdateMessageErrorSubcode::UnrecognizedWellKnownAttribute,
+                    raw,
+                    anyhow::anyhow!("unrecognized well-known attribute: {type_code}"),
+                ));
+            }
+            if !flags.transitive() {
+                return Ok((None, raw.len()));
+            }
+
+            return Ok((
+                Some(Self::Unknown {
+                    flags: flags.with_partial(true).with_extended_length(false),
+                    type_code,
+                    value: bytes::BytesMut::from(body),
+                }),
+                raw.len(),
+            ));
+        };
+
+        // only optional transitive attributes can be partial (RFC 4271 4.3)
+        if flags.optional() != expected.optional()
+            || flags.transitive() != expected.transitive()
+            || (flags.partial() && !(flags.optional() && flags.transitive()))
+        {
+            return Err(update_message_error(
+                UpdateMessageErrorSubcode::AttributeFlagsError,
+                raw,
+                anyhow::anyhow!("invalid flags for attribute {type_code}: {:?}", flags),
+            ));
+        }
+
+        let expected_len = match type_code {
+            Self::ORIGIN => Some(1),
+            Self::NEXT_HOP | Self::MULTI_EXIT_DISC | Self::LOCAL_PREF => Some(4),
+            Self::ATOMIC_AGGREGATE => Some(0),
+            Self::AGGREGATOR if four_octet_as => Some(8),
+            Self::AGGREGATOR => Some(6),
+            _ => None,
+        };
+        // address families that are not supported can never have been negotiated, so
+        // their routes are ignored instead of resetting the session (RFC 7606 7.3)
+        if matches!(type_code, Self::MP_REACH_NLRI | Self::MP_UNREACH_NLRI)
+            && len >= 3
+            && (crate::types::Afi::try_from(u16::from_be_bytes([body[0], body[1]])).is_err()
+                || crate::types::Safi::try_from(body[2]).is_err())
+        {
+            tracing::debug!("ignoring attribute {type_code} for an unsupported address family");
+            return Ok((None, raw.len()));
+        }
+
+        // malformed AS4_* attributes are discarded rather than resetting the session
+        match type_code {
+            Self::AS4_PATH => {
+                return Ok((
+                    AsPath::decode(body, true).ok().map(Self::As4Path),
+                    raw.len(),
+                ))
+            }
+            Self::AS4_AGGREGATOR if len != 8 => return Ok((None, raw.len())),
+            Self::AS4_AGGREGATOR => {
+                return Ok((
+                    Some(Self::As4Aggregator(
+                        crate::types::ASNum::from(u32::from_be_bytes([
+                            body[0], body[1], body[2], body[3],
+                        ])),
+                        std::net::Ipv4Addr::new(body[4], body[5], body[6], body[7]),
+                    )),
+                    raw.len(),
+                ))
+            }
+            _ => {}
+        }
+        if expected_len.is_some_and(|expected_len| expected_len != len) {
+            return Err(update_message_error(
+                UpdateMessageErrorSubcode::AttributeLengthError,
+                raw,
+                anyhow::anyhow!("invalid length for attribute {type_code}: {len}"),
+            ));
+        }
+
+        let attribute = match type_code {
+            Self::ORIGIN => Self::Origin(Origin::try_from(body[0]).map_err(|e| {
+                update_message_error(UpdateMessageErrorSubcode::InvalidOriginAttribute, raw, e)
+            })?),
+            Self::AS_PATH => Self::AsPath(AsPath::decode(body, four_octet_as).map_err(|e| {
+                update_message_error(UpdateMessageErrorSubcode::MalformedAsPath, &[], e)
+            })?),
+            Self::NEXT_HOP => {
+                let next_hop = std::net::Ipv4Addr::new(body[0], body[1], body[2], body[3]);
+                if next_hop.is_unspecified() || next_hop.is_multicast() || next_hop.is_broadcast() {
+                    return Err(update_message_error(
+                        UpdateMessageErrorSubcode::InvalidNextHopAttribute,
+                        raw,
+                        anyhow::anyhow!("invalid next hop: {next_hop}"),
+                    ));
+                }
+                Self::NextHop(next_hop)
+            }
+            Self::MULTI_EXIT_DISC => {
+                Self::MultiExitDisc(u32::from_be_bytes([body[0], body[1], body[2], body[3]]))
+            }
+            Self::LOCAL_PREF => {
+                Self::LocalPref(u32::from_be_bytes([body[0], body[1], body[2], body[3]]))
+            }
+            Self::ATOMIC_AGGREGATE => Self::AtomicAggregate,
+            Self::MP_REACH_NLRI => Self::MpReachNlri(MpReachNlri::decode(body).map_err(|e| {
+                update_message_error(UpdateMessageErrorSubcode::OptionalAttributeError, raw, e)
+            })?),
+            Self::MP_UNREACH_NLRI => {
+                Self::MpUnreachNlri(MpUnreachNlri::decode(body).map_err(|e| {
+                    update_message_error(UpdateMessageErrorSubcode::OptionalAttributeError, raw, e)
+                })?)
+            }
+            _ => {
+                let (asn, addr) = body.split_at(len - 4);
+                Self::Aggregator {
+                    asn: crate::types::ASNum::from(if four_octet_as {
+                        u32::from_be_bytes([asn[0], asn[1], asn[2], asn[3]])
+                    } else {
+                        u16::from_be_bytes([asn[0], asn[1]]) as u32
+                    }),
+                    addr: std::net::Ipv4Addr::new(addr[0], addr[1], addr[2], addr[3]),
+                    partial: flags.partial(),
+                }
+            }
+        };
+
+        Ok((Some(attribute), raw.len()))
+    }
+}
+
+fn update_message_error(
+    subcode: crate::packet::notification::UpdateMessageErrorSubcode,
+    data: &[u8],
+    src: anyhow::Error,
+) -> crate::error::ConvertBytesErr {
+    crate::error::ConvertBytesErr::new(
+        crate::packet::notification::ErrorCode::UpdateMessage(subcode),
+        data,
+        src,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_subcode(
+        err: crate::error::ConvertBytesErr,
+    ) -> crate::packet::notification::UpdateMessageErrorSubcode {
+        match err.notification().error_code() {
+            crate::packet::notification::ErrorCode::UpdateMessage(subcode) => subcode.clone(),
+            code => panic!("unexpected error code: {:?}", code),
+        }
+    }
+
+    #[test]
+    fn convert_bytes_attributes() {
+        let expected = vec![
+            PathAttribute::Origin(Origin::Igp),
+            PathAttribute::AsPath(AsPath::new(vec![
+                AsPathSegment::AsSequence(vec![64512.into(), 64513.into()]),
+                AsPathSegment::AsSet(vec![64514.into()]),
+            ])),
+            PathAttribute::NextHop("10.0.0.1".parse().unwrap()),
+            PathAttribute::MultiExitDisc(100),
+            PathAttribute::LocalPref(200),
+            PathAttribute::AtomicAggregate,
+            PathAttribute::Aggregator {
+                asn: 64512.into(),
+                addr: "10.0.0.2".parse().unwrap(),
+                partial: false,
+            },
+        ];
+
+        let mut bytes = bytes::BytesMut::new();
+        for attribute in &expected {
+            attribute.encode(&mut bytes, true);
+        }
+
+        assert_eq!(PathAttribute::decode_all(&bytes, true).unwrap(), expected);
+    }
+
+    #[test]
+    fn extended_length() {
+        let expected = PathAttribute::AsPath(AsPath::new(vec![AsPathSegment::AsSequence(
+            (0..200).map(crate::types::ASNum::from).collect(),
+        )]));
+        let mut bytes = bytes::BytesMut::new();
+        expected.encode(&mut bytes, true);
+
+        assert!(AttributeFlags::from(bytes[0]).extended_length());
+        assert_eq!(
+            PathAttribute::decode_all(&bytes, true).unwrap(),
+            vec![expected]
+        );
+    }
+
+    #[test]
+    fn unknown_optional_transitive_attribute_is_partial() {
+        let attributes = PathAttribute::decode_all(&[0xc0, 0x20, 0x02, 0xaa, 0xbb], true).unwrap();
+        assert_eq!(
+            attributes,
+            vec![PathAttribute::Unknown {
+                flags: AttributeFlags::new(true, true).with_partial(true),
+                type_code: 0x20,
+                value: bytes::BytesMut::from(&[0xaa, 0xbb][..]),
+            }]
+        );
+
+        let mut bytes = bytes::BytesMut::new();
+        attributes[0].encode(&mut bytes, true);
+        assert_eq!(&bytes[..], &[0xe0, 0x20, 0x02, 0xaa, 0xbb]);
+    }
+
+    #[test]
+    fn unknown_optional_non_transitive_attribute_is_ignored() {
+        let attributes = PathAttribute::decode_all(&[0x80, 0x20, 0x01, 0xaa], true).unwrap();
+        assert!(attributes.is_empty());
+    }
+
+    #[test]
+    fn unrecognized_well_known_attribute() {
+        let err = PathAttribute::decode_all(&[0x40, 0x20, 0x01, 0xaa], true).unwrap_err();
+        assert_eq!(
+            error_subcode(err),
+            crate::packet::notification::UpdateMessageErrorSubcode::UnrecognizedWellKnownAttribute
+        );
+    }
+
+    #[test]
+    fn attribute_flags_error() {
+        let err = PathAttribute::decode_all(&[0x80, 0x01, 0x01, 0x00], true).unwrap_err();
+        assert_eq!(
+            error_subcode(err),
+            crate::packet::notification::UpdateMessageErrorSubcode::AttributeFlagsError
+        );
+    }
+
+    #[test]
+    fn attribute_length_error() {
+        let err = PathAttribute::decode_all(&[0x40, 0x03, 0x03, 10, 0, 0], true).unwrap_err();
+        assert_eq!(
+            error_subcode(err),
+            crate::packet::notification::UpdateMessageErrorSubcode::AttributeLengthError
+        );
+    }
+
+    #[test]
+    fn invalid_origin() {
+        let err = PathAttribute::decode_all(&[0x40, 0x01, 0x01, 0x03], true).unwrap_err();
+        assert_eq!(
+            error_subcode(err),
+            crate::packet::notification::UpdateMessageErrorSubcode::InvalidOriginAttribute
+        );
+    }
+
+    #[test]
+    fn malformed_as_path() {
+        let err = PathAttribute::decode_all(&[0x40, 0x02, 0x04, 0x02, 0x02, 0xfc, 0x00], true)
+            .unwrap_err();
+        assert_eq!(
+            error_subcode(err),
+            crate::packet::notification::UpdateMessageErrorSubcode::MalformedAsPath
+        );
+    }
+
+    #[test]
+    fn duplicate_attribute() {
+        let err =
+            PathAttribute::decode_all(&[0x40, 0x01, 0x01, 0x00, 0x40, 0x01, 0x01, 0x00], true)
+                .unwrap_err();
+        assert_eq!(
+            error_subcode(err),
+            crate::packet::notification::UpdateMessageErrorSubcode::MalformedAttributeList
+        );
+    }
+
+    #[test]
+    fn two_octet_session_uses_as4_attributes() {
+        let expected = vec![
+            PathAttribute::AsPath(AsPath::new(vec![AsPathSegment::AsSequence(vec![
+                64512.into(),
+                4200000000.into(),
+            ])])),
+            PathAttribute::Aggregator {
+                asn: 4200000001.into(),
+                addr: "10.0.0.2".parse().unwrap(),
+                partial: false,
+            },
+        ];
+
+        let mut bytes = bytes::BytesMut::new();
+        PathAttribute::encode_all(&expected, &mut bytes, false);
+
+        // the AS4_* attributes follow AS_PATH and AGGREGATOR carrying AS_TRANS
+        assert_eq!(&bytes[3..9], &[2, 2, 0xfc, 0x00, 0x5b, 0xa0]);
+        assert_eq!(PathAttribute::decode_all(&bytes, false).unwrap(), expected);
+    }
+
+    #[test]
+    fn four_octet_session_discards_as4_attributes() {
+        let as_path = PathAttribute::AsPath(AsPath::new(vec![AsPathSegment::AsSequence(vec![
+            4200000000.into(),
+        ])]));
+        let mut bytes = bytes::BytesMut::new();
+        as_path.encode(&mut bytes, true);
+        PathAttribute::As4Path(AsPath::new(vec![AsPathSegment::AsSequence(vec![
+            4200000001.into(),
+        ])]))
+        .encode(&mut bytes, true);
+
+        assert_eq!(
+            PathAttribute::decode_all(&bytes, true).unwrap(),
+            vec![as_path]
+        );
+    }
+
+    #[test]
+    fn merge_as4_path() {
+        let as_path = AsPath::new(vec![
+            AsPathSegment::AsSequence(vec![64512.into(), 23456.into(), 23456.into()]),
+            AsPathSegment::AsSet(vec![64513.into()]),
+        ]);
+        let as4_path = AsPath::new(vec![
+            AsPathSegment::AsSequence(vec![4200000000.into(), 4200000001.into()]),
+            AsPathSegment::AsSet(vec![64513.into()]),
+        ]);
+
+        assert_eq!(
+            as_path.merge_as4_path(&as4_path),
+            AsPath::new(vec![
+                AsPathSegment::AsSequence(vec![64512.into()]),
+                AsPathSegment::AsSequence(vec![4200000000.into(), 4200000001.into()]),
+                AsPathSegment::AsSet(vec![64513.into()]),
+            ])
+        );
+
+        // an AS4_PATH longer than AS_PATH is ignored
+        assert_eq!(
+            AsPath::new(vec![AsPathSegment::AsSequence(vec![23456.into()])])
+                .merge_as4_path(&as4_path),
+            AsPath::new(vec![AsPathSegment::AsSequence(vec![23456.into()])])
+        );
+    }
+
+    #[test]
+    fn as4_aggregator_is_ignored_without_as_trans() {
+        let mut bytes = bytes::BytesMut::new();
+        PathAttribute::Aggregator {
+            asn: 64512.into(),
+            addr: "10.0.0.2".parse().unwrap(),
+            partial: false,
+        }
+        .encode(&mut bytes, false);
+        PathAttribute::As4Aggregator(4200000000.into(), "10.0.0.3".parse().unwrap())
+            .encode(&mut bytes, false);
+
+        assert_eq!(
+            PathAttribute::decode_all(&bytes, false).unwrap(),
+            vec![PathAttribute::Aggregator {
+                asn: 64512.into(),
+                addr: "10.0.0.2".parse().unwrap(),
+                partial: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn convert_bytes_mp_attributes() {
+        let expected = vec![
+            PathAttribute::MpReachNlri(MpReachNlri {
+                afi: crate::types::Afi::Ipv6,
+                safi: crate::types::Safi::Unicast,
+                next_hop: NextHop::V6 {
+                    global: "2001:db8::1".parse().unwrap(),
+                    link_local: Some("fe80::1".parse().unwrap()),
+                },
+                nlri: vec![crate::packet::prefix::Ipv6Prefix::new(
+                    "2001:db8:1::".parse().unwrap(),
+                    48,
+                )
+                .unwrap()
+                .into()],
+            }),
+            PathAttribute::MpUnreachNlri(MpUnreachNlri {
+                afi: crate::types::Afi::Ipv4,
+                safi: crate::types::Safi::Unicast,
+                withdrawn_routes: vec![crate::packet::prefix::Ipv4Prefix::new(
+                    "10.0.0.0".parse().unwrap(),
+                    8,
+                )
+                .unwrap()
+                .into()],
+            }),
+        ];
+
+        let mut bytes = bytes::BytesMut::new();
+        for attribute in &expected {
+            attribute.encode(&mut bytes, true);
+        }
+
+        assert_eq!(PathAttribute::decode_all(&bytes, true).unwrap(), expected);
+    }
+
+    #[test]
+    fn malformed_mp_reach_nlri() {
+        // an IPv6 next hop must be 16 or 32 octets long
+        let err = PathAttribute::decode_all(&[0x80, 0x0e, 0x09, 0, 2, 1, 4, 10, 0, 0, 1, 0], true)
+            .unwrap_err();
+        assert_eq!(
+            error_subcode(err),
+            crate::packet::notification::UpdateMessageErrorSubcode::OptionalAttributeError
+        );
+    }
+
+    #[test]
+    fn prepend_as_path() {
+        let mut as_path = AsPath::default();
+        as_path.prepend(64513.into());
+        assert_eq!(
+            as_path,
+            AsPath::new(vec![AsPathSegment::AsSequence(vec![64513.into()])])
+        );
+
+        as_path.prepend(64512.into());
+        assert_eq!(
+            as_path,
+            AsPath::new(vec![AsPathSegment::AsSequence(vec![
+                64512.into(),
+                64513.into()
+            ])])
+        );
+        assert!(as_path.contains(64513.into()));
+        assert!(!as_path.contains(64514.into()));
+
+        let mut as_path = AsPath::new(vec![AsPathSegment::AsSet(vec![64513.into()])]);
+        as_path.prepend(64512.into());
+        assert_eq!(
+            as_path,
+            AsPath::new(vec![
+                AsPathSegment::AsSequence(vec![64512.into()]),
+                AsPathSegment::AsSet(vec![64513.into()]),
+            ])
+        );
+    }
+
+    #[test]
+    fn partial_bit_is_kept() {
+        // AGGREGATOR with the Partial bit set by a speaker along the path
+        let bytes = [0xe0, 7, 8, 0, 0, 0xfc, 0x00, 10, 0, 0, 2];
+        let attributes = PathAttribute::decode_all(&bytes, true).unwrap();
+        assert_eq!(
+            attributes,
+            vec![PathAttribute::Aggregator {
+                asn: 64512.into(),
+                addr: "10.0.0.2".parse().unwrap(),
+                partial: true,
+            }]
+        );
+
+        let mut encoded = bytes::BytesMut::new();
+        PathAttribute::encode_all(&attributes, &mut encoded, true);
+        assert_eq!(&encoded[..], &bytes);
+    }
+
+    #[test]
+    fn unsupported_address_families_are_ignored() {
+        let origin = PathAttribute::Origin(Origin::Igp);
+        let mut bytes = bytes::BytesMut::new();
+        origin.encode(&mut bytes, true);
+        // MP_REACH_NLRI for L2VPN EVPN
+        bytes.extend_from_slice(&[0x80, 14, 9, 0, 25, 70, 4, 10, 0, 0, 1, 0]);
+        // MP_UNREACH_NLRI for IPv4 multicast
+        bytes.extend_from_slice(&[0x80, 15, 5, 0, 1, 2, 8, 10]);
+
+        assert_eq!(
+            PathAttribute::decode_all(&bytes, true).unwrap(),
+            vec![origin]
+        );
+    }
+
+    #[test]
+    fn partial_optional_non_transitive_attribute() {
+        // MULTI_EXIT_DISC with the Partial bit set
+        let raw = [0xa0, 0x04, 0x04, 0, 0, 0, 10];
+        let err = PathAttribute::decode_all(&raw, true).unwrap_err();
+        assert_eq!(err.notification().data(), &raw[..]);
+        assert_eq!(
+            error_subcode(err),
+            crate::packet::notification::UpdateMessageErrorSubcode::AttributeFlagsError
+        );
+
+        // the Partial bit is still fine on optional transitive attributes
+        assert!(
+            PathAttribute::decode_all(&[0xe0, 7, 8, 0, 0, 0xfc, 0x00, 10, 0, 0, 2], true).is_ok()
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use bytes::BufMut as _;
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum Capability {
+    Multiprotocol(crate::types::Afi, crate::types::Safi),
+    FourOctetAs(crate::types::ASNum),
+    Unknown { code: u8, value: bytes::BytesMut },
+}
+
+impl Capability {
+    const MULTIPROTOCOL: u8 = 1;
+    const FOUR_OCTET_AS: u8 = 65;
+
+    pub fn code(&self) -> u8 {
+        match self {
+            Self::Multiprotocol(_, _) => Self::MULTIPROTOCOL,
+            Self::FourOctetAs(_) => Self::FOUR_OCTET_AS,
+            Self::Unknown { code, .. } => *code,
+        }
+    }
+
+    pub fn encode(&self, bytes: &mut bytes::BytesMut) {
+        let mut value = bytes::BytesMut::new();
+        match self {
+            Self::Multiprotocol(afi, safi) => {
+                value.put_u16((*afi).into());
+                value.put_u8(0);
+                value.put_u8((*safi).into());
+            }
+            Self::FourOctetAs(asn) => value.put_u32((*asn).into()),
+            Self::Unknown { value: v, .. } => value.put(&v[..]),
+        }
+
+        bytes.put_u8(self.code());
+        bytes.put_u8(value.len() as u8);
+        bytes.put(value);
+    }
+
+    // capabilities carrying per-speaker values, such as the AS number, only
+    // have to agree on the kind of capability
+    pub fn is_compatible(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::FourOctetAs(_), Self::FourOctetAs(_)) => true,
+            _ => self == other,
+        }
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        let mut bytes = bytes::BytesMut::new();
+        self.encode(&mut bytes);
+        bytes.len()
+    }
+
+    // capabilities that are not understood, including unknown AFI/SAFI pairs,
+    // are kept as unknown so that they can be ignored as RFC 5492 requires
+    pub fn decode_all(value: &[u8]) -> anyhow::Result<Vec<Self>> {
+        let mut capabilities = Vec::new();
+        let mut rest = value;
+        while !rest.is_empty() {
+            if rest.len() < 2 || rest.len() < 2 + rest[1] as usize {
+                return Err(anyhow::anyhow!("truncated capability: {:?}", rest));
+            }
+
+            let code = rest[0];
+            let body = &rest[2..2 + rest[1] as usize];
+            let capability = match code {
+                Self::MULTIPROTOCOL if body.len() == 4 => {
+                    match (
+                        crate::types::Afi::try_from(u16::from_be_bytes([body[0], body[1]])),
+                        crate::types::Safi::try_from(body[3]),
+                    ) {
+                        (Ok(afi), Ok(safi)) => Some(Self::Multiprotocol(afi, safi)),
+                        _ => None,
+                    }
+                }
+                Self::MULTIPROTOCOL => {
+                    return Err(anyhow::anyhow!(
+                        "invalid multiprotocol capability length: {}",
+                        body.len()
+                    ))
+                }
+                Self::FOUR_OCTET_AS if body.len() == 4 => {
+                    Some(Self::FourOctetAs(crate::types::ASNum::from(
+                        u32::from_be_bytes([body[0], body[1], body[2], body[3]]),
+                    )))
+                }
+                Self::FOUR_OCTET_AS => {
+                    return Err(anyhow::anyhow!(
+                        "invalid four-octet AS capability length: {}",
+                        body.len()
+                    ))
+                }
+                _ => None,
+            };
+            capabilities.push(capability.unwrap_or_else(|| Self::Unknown {
+                code,
+                value: bytes::BytesMut::from(body),
+            }));
+            rest = &rest[2 + body.len()..];
+        }
+
+        Ok(capabilities)
+    }
+}
+
+// the names capabilities are configured with
+impl std::str::FromStr for Capability {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ipv4-unicast" => Ok(Self::Multiprotocol(
+                crate::types::Afi::Ipv4,
+                crate::types::Safi::Unicast,
+            )),
+            "ipv6-unicast" => Ok(Self::Multiprotocol(
+                crate::types::Afi::Ipv6,
+                crate::types::Safi::Unicast,
+            )),
+            // the AS number is filled in when the capability is advertised
+            "four-octet-as" => Ok(Self::FourOctetAs(crate::types::ASNum::from(0))),
+            _ => Err(anyhow::anyhow!(
+                "unknown capability `{s}`, expected ipv4-unicast, ipv6-unicast or four-octet-as"
+            )),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Capability {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_bytes_capabilities() {
+        let expected = vec![
+            Capability::Multiprotocol(crate::types::Afi::Ipv4, crate::types::Safi::Unicast),
+            Capability::Unknown {
+                code: 2,
+                value: bytes::BytesMut::new(),
+            },
+        ];
+        let mut bytes = bytes::BytesMut::new();
+        for capability in &expected {
+            capability.encode(&mut bytes);
+        }
+
+        assert_eq!(&bytes[..], &[1, 4, 0, 1, 0, 1, 2, 0]);
+        assert_eq!(Capability::decode_all(&bytes).unwrap(), expected);
+    }
+
+    #[test]
+    fn unknown_afi_is_kept_as_unknown() {
+        let capabilities = Capability::decode_all(&[1, 4, 0, 25, 0, 70]).unwrap();
+        assert_eq!(
+            capabilities,
+            vec![Capability::Unknown {
+                code: 1,
+                value: bytes::BytesMut::from(&[0, 25, 0, 70][..]),
+            }]
+        );
+    }
+
+    #[test]
+    fn truncated_capability() {
+        assert!(Capability::decode_all(&[1]).is_err());
+        assert!(Capability::decode_all(&[1, 4, 0, 1]).is_err());
+        assert!(Capability::decode_all(&[1, 2, 0, 1]).is_err());
+    }
+
+    #[test]
+    fn convert_bytes_four_octet_as() {
+        let expected = vec![Capability::FourOctetAs(4200000000.into())];
+        let mut bytes = bytes::BytesMut::new();
+        expected[0].encode(&mut bytes);
+
+        assert_eq!(&bytes[..], &[65, 4, 0xfa, 0x56, 0xea, 0x00]);
+        assert_eq!(Capability::decode_all(&bytes).unwrap(), expected);
+        assert!(Capability::decode_all(&[65, 2, 0, 1]).is_err());
+    }
+
+    #[test]
+    fn capability_names() {
+        assert_eq!(
+            "ipv6-unicast".parse::<Capability>().unwrap(),
+            Capability::Multiprotocol(crate::types::Afi::Ipv6, crate::types::Safi::Unicast)
+        );
+        assert!("four-octet-as"
+            .parse::<Capability>()
+            .unwrap()
+            .is_compatible(&Capability::FourOctetAs(64512.into())));
+        assert!("ipv4-multicast".parse::<Capability>().is_err());
+    }
+}
@@ -0,0 +1,139 @@
+use bytes::BufMut as _;
+
+// AS numbers are two octets wide until both speakers advertise the
+// four-octet AS capability
+#[derive(Debug, Clone, Default)]
+pub struct MessageCodec {
+    four_octet_as: bool,
+}
+
+impl MessageCodec {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn set_four_octet_as(&mut self, four_octet_as: bool) {
+        self.four_octet_as = four_octet_as;
+    }
+}
+
+impl tokio_util::codec::Decoder for MessageCodec {
+    type Item = crate::packet::message::Message;
+    type Error = crate::error::CodecErr;
+
+    fn decode(&mut self, src: &mut bytes::BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if src.len() < crate::constants::HEADER_LEN {
+            src.reserve(crate::constants::HEADER_LEN - src.len());
+            return Ok(None);
+        }
+
+        // validates the marker and the length before waiting for the rest of the message
+        let header = crate::packet::hdr::Header::try_from(bytes::BytesMut::from(
+            &src[..crate::constants::HEADER_LEN],
+        ))?;
+        let length = header.length() as usize;
+        if src.len() < length {
+            src.reserve(length - src.len());
+            return Ok(None);
+        }
+
+        let message = src.split_to(length);
+        Ok(Some(crate::packet::message::Message::decode(
+            message,
+            self.four_octet_as,
+        )?))
+    }
+}
+
+impl tokio_util::codec::Encoder<crate::packet::message::Message> for MessageCodec {
+    type Error = crate::error::CodecErr;
+
+    fn encode(
+        &mut self,
+        item: crate::packet::message::Message,
+        dst: &mut bytes::BytesMut,
+    ) -> Result<(), Self::Error> {
+        dst.put(item.encode(self.four_octet_as));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_util::codec::{Decoder as _, Encoder as _};
+
+    fn error_code(err: crate::error::CodecErr) -> crate::packet::notification::ErrorCode {
+        match err {
+            crate::error::CodecErr::Message(e) => e.notification().error_code().clone(),
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn decode_partial_message() {
+        let mut codec = MessageCodec::new();
+        let expected = crate::packet::message::Message::new_keepalive();
+        let bytes: bytes::BytesMut = expected.clone().into();
+
+        let mut src = bytes::BytesMut::new();
+        for b in &bytes[..bytes.len() - 1] {
+            src.put_u8(*b);
+            assert!(codec.decode(&mut src).unwrap().is_none());
+        }
+        src.put_u8(bytes[bytes.len() - 1]);
+
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(expected));
+        assert!(src.is_empty());
+    }
+
+    #[test]
+    fn decode_multiple_messages() {
+        let mut codec = MessageCodec::new();
+        let open = crate::packet::message::Message::new_open(
+            64512.into(),
+            "127.0.0.1".parse().unwrap(),
+            crate::types::HoldTime::new(),
+            vec![],
+        );
+        let keepalive = crate::packet::message::Message::new_keepalive();
+
+        let mut src = bytes::BytesMut::new();
+        codec.encode(open.clone(), &mut src).unwrap();
+        codec.encode(keepalive.clone(), &mut src).unwrap();
+
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(open));
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(keepalive));
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+    }
+
+    #[test]
+    fn decode_invalid_marker() {
+        let mut codec = MessageCodec::new();
+        let mut src: bytes::BytesMut = crate::packet::message::Message::new_keepalive().into();
+        src[0] = 0;
+
+        assert_eq!(
+            error_code(codec.decode(&mut src).unwrap_err()),
+            crate::packet::notification::ErrorCode::MessageHeader(
+                crate::packet::notification::MessageHeaderErrorSubcode::ConnectionNotSynchronized
+            )
+        );
+    }
+
+    #[test]
+    fn decode_invalid_length() {
+        let mut codec = MessageCodec::new();
+        for length in [0u16, 18, 4097, u16::MAX] {
+            let mut src: bytes::BytesMut = crate::packet::message::Message::new_keepalive().into();
+            src[16..18].copy_from_slice(&length.to_be_bytes());
+
+            assert_eq!(
+                error_code(codec.decode(&mut src).unwrap_err()),
+                crate::packet::notification::ErrorCode::MessageHeader(
+                    crate::packet::notification::MessageHeaderErrorSubcode::BadMessageLength
+                )
+            );
+        }
+    }
+}
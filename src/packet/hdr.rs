@@ -15,13 +15,76 @@ impl Header {
     }
 }
 
+impl Header {
+    pub fn length(&self) -> u16 {
+        self.length
+    }
+
+    pub fn check_length(&self, len: usize) -> Result<(), crate::error::ConvertBytesErr> {
+        if self.length as usize != len {
+            return Err(crate::error::ConvertBytesErr::new(
+                crate::packet::notification::ErrorCode::MessageHeader(
+                    crate::packet::notification::MessageHeaderErrorSubcode::BadMessageLength,
+                ),
+                &self.length.to_be_bytes(),
+                anyhow::anyhow!(
+                    "message length {} does not match header length {}",
+                    len,
+                    self.length
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
 impl TryFrom<bytes::BytesMut> for Header {
     type Error = crate::error::ConvertBytesErr;
 
     fn try_from(value: bytes::BytesMut) -> Result<Self, Self::Error> {
-        let _marker = &value[0..16];
+        if value.len() < crate::constants::HEADER_LEN {
+            return Err(Self::Error::new(
+                crate::packet::notification::ErrorCode::MessageHeader(
+                    crate::packet::notification::MessageHeaderErrorSubcode::BadMessageLength,
+                ),
+                &(value.len() as u16).to_be_bytes(),
+                anyhow::anyhow!("header is too short: {}", value.len()),
+            ));
+        }
+
+        if value[0..16].iter().any(|b| *b != 0xff) {
+            return Err(Self::Error::new(
+                crate::packet::notification::ErrorCode::MessageHeader(
+                    crate::packet::notification::MessageHeaderErrorSubcode::ConnectionNotSynchronized,
+                ),
+                &[],
+                anyhow::anyhow!("invalid marker: {:?}", &value[0..16]),
+            ));
+        }
+
         let length = u16::from_be_bytes([value[16], value[17]]);
-        let message_type = value[18].try_into()?;
+        if !(crate::constants::HEADER_LEN..=crate::constants::MAX_MESSAGE_LEN)
+            .contains(&(length as usize))
+        {
+            return Err(Self::Error::new(
+                crate::packet::notification::ErrorCode::MessageHeader(
+                    crate::packet::notification::MessageHeaderErrorSubcode::BadMessageLength,
+                ),
+                &value[16..18],
+                anyhow::anyhow!("invalid message length: {length}"),
+            ));
+        }
+
+        let message_type = MessageType::try_from(value[18]).map_err(|e| {
+            Self::Error::new(
+                crate::packet::notification::ErrorCode::MessageHeader(
+                    crate::packet::notification::MessageHeaderErrorSubcode::BadMessageType,
+                ),
+                &value[18..19],
+                e,
+            )
+        })?;
 
         Ok(Self {
             length,
@@ -93,6 +156,48 @@ mod tests {
         assert_eq!(header.message_type, MessageType::Open);
     }
 
+    #[test]
+    fn test_header_errors() {
+        let mut bytes = bytes::BytesMut::with_capacity(19);
+        bytes.extend_from_slice(&[0xff; 15]);
+        bytes.extend_from_slice(&[0]);
+        bytes.extend_from_slice(&19u16.to_be_bytes());
+        bytes.extend_from_slice(&[1]);
+        let err = Header::try_from(bytes).unwrap_err();
+        assert_eq!(
+            err.notification().error_code(),
+            &crate::packet::notification::ErrorCode::MessageHeader(
+                crate::packet::notification::MessageHeaderErrorSubcode::ConnectionNotSynchronized
+            )
+        );
+
+        let mut bytes = bytes::BytesMut::with_capacity(19);
+        bytes.extend_from_slice(&[0xff; 16]);
+        bytes.extend_from_slice(&4097u16.to_be_bytes());
+        bytes.extend_from_slice(&[1]);
+        let err = Header::try_from(bytes).unwrap_err();
+        assert_eq!(
+            err.notification().error_code(),
+            &crate::packet::notification::ErrorCode::MessageHeader(
+                crate::packet::notification::MessageHeaderErrorSubcode::BadMessageLength
+            )
+        );
+        assert_eq!(err.notification().data(), &4097u16.to_be_bytes());
+
+        let mut bytes = bytes::BytesMut::with_capacity(19);
+        bytes.extend_from_slice(&[0xff; 16]);
+        bytes.extend_from_slice(&19u16.to_be_bytes());
+        bytes.extend_from_slice(&[5]);
+        let err = Header::try_from(bytes).unwrap_err();
+        assert_eq!(
+            err.notification().error_code(),
+            &crate::packet::notification::ErrorCode::MessageHeader(
+                crate::packet::notification::MessageHeaderErrorSubcode::BadMessageType
+            )
+        );
+        assert_eq!(err.notification().data(), &[5]);
+    }
+
     #[test]
     fn test_message_type_try_from() {
         assert_eq!(MessageType::try_from(1).unwrap(), MessageType::Open);
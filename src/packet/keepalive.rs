@@ -0,0 +1,69 @@
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct KeepaliveMessage {
+    hdr: crate::packet::hdr::Header,
+}
+
+impl KeepaliveMessage {
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl Default for KeepaliveMessage {
+    fn default() -> Self {
+        Self {
+            hdr: crate::packet::hdr::Header::new(
+                crate::constants::HEADER_LEN as u16,
+                crate::packet::hdr::MessageType::KeepAlive,
+            ),
+        }
+    }
+}
+
+impl TryFrom<bytes::BytesMut> for KeepaliveMessage {
+    type Error = crate::error::ConvertBytesErr;
+
+    fn try_from(value: bytes::BytesMut) -> Result<Self, Self::Error> {
+        let hdr = crate::packet::hdr::Header::try_from(value.clone())?;
+        hdr.check_length(value.len())?;
+        if value.len() != crate::constants::HEADER_LEN {
+            return Err(Self::Error::new(
+                crate::packet::notification::ErrorCode::MessageHeader(
+                    crate::packet::notification::MessageHeaderErrorSubcode::BadMessageLength,
+                ),
+                &(value.len() as u16).to_be_bytes(),
+                anyhow::anyhow!("invalid keepalive message length: {}", value.len()),
+            ));
+        }
+
+        Ok(Self { hdr })
+    }
+}
+
+impl From<KeepaliveMessage> for bytes::BytesMut {
+    fn from(msg: KeepaliveMessage) -> bytes::BytesMut {
+        msg.hdr.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_bytes_message() {
+        let expected = KeepaliveMessage::new();
+        let keepalive_message_bytes: bytes::BytesMut = expected.clone().into();
+        assert_eq!(keepalive_message_bytes.len(), crate::constants::HEADER_LEN);
+
+        let keepalive_message: KeepaliveMessage = keepalive_message_bytes.try_into().unwrap();
+        assert_eq!(keepalive_message, expected);
+    }
+
+    #[test]
+    fn invalid_length() {
+        let mut bytes: bytes::BytesMut = KeepaliveMessage::new().into();
+        bytes.extend_from_slice(&[0]);
+        assert!(KeepaliveMessage::try_from(bytes).is_err());
+    }
+}
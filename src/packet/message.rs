@@ -1,21 +1,17 @@
 #[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub enum Message {
     Open(crate::packet::open::OpenMessage),
+    Update(crate::packet::update::UpdateMessage),
+    Notification(crate::packet::notification::NotificationMessage),
+    KeepAlive(crate::packet::keepalive::KeepaliveMessage),
 }
 
-impl TryFrom<bytes::BytesMut> for Message {
-    type Error = crate::error::ConvertBytesErr;
-
-    fn try_from(bytes: bytes::BytesMut) -> Result<Self, Self::Error> {
-        if bytes.len() < crate::constants::HEADER_LEN {
-            return Err(Self::Error::from(anyhow::anyhow!(
-                "message length is less than header length"
-            )));
-        }
-
-        let header = crate::packet::hdr::Header::try_from(bytes::BytesMut::from(
-            &bytes[..crate::constants::HEADER_LEN],
-        ))?;
+impl Message {
+    pub fn decode(
+        bytes: bytes::BytesMut,
+        four_octet_as: bool,
+    ) -> Result<Self, crate::error::ConvertBytesErr> {
+        let header = crate::packet::hdr::Header::try_from(bytes.clone())?;
 
         match header.message_type {
             crate::packet::hdr::MessageType::Open => {
@@ -23,21 +19,212 @@ impl TryFrom<bytes::BytesMut> for Message {
 
                 Ok(Self::Open(open))
             }
-            _ => Err(Self::Error::from(anyhow::anyhow!("unknown message type"))),
+            crate::packet::hdr::MessageType::Update => {
+                let update = crate::packet::update::UpdateMessage::decode(bytes, four_octet_as)?;
+
+                Ok(Self::Update(update))
+            }
+            crate::packet::hdr::MessageType::Notification => {
+                let notification =
+                    crate::packet::notification::NotificationMessage::try_from(bytes)
+                        .map_err(crate::error::ConvertBytesErr::received_in_notification)?;
+
+                Ok(Self::Notification(notification))
+            }
+            crate::packet::hdr::MessageType::KeepAlive => {
+                let keepalive = crate::packet::keepalive::KeepaliveMessage::try_from(bytes)?;
+
+                Ok(Self::KeepAlive(keepalive))
+            }
+        }
+    }
+
+    pub fn encode(self, four_octet_as: bool) -> bytes::BytesMut {
+        match self {
+            Message::Open(open) => open.into(),
+            Message::Update(update) => update.encode(four_octet_as),
+            Message::Notification(notification) => notification.into(),
+            Message::KeepAlive(keepalive) => keepalive.into(),
         }
     }
 }
 
+impl TryFrom<bytes::BytesMut> for Message {
+    type Error = crate::error::ConvertBytesErr;
+
+    fn try_from(bytes: bytes::BytesMut) -> Result<Self, Self::Error> {
+        Self::decode(bytes, true)
+    }
+}
+
 impl From<Message> for bytes::BytesMut {
     fn from(msg: Message) -> bytes::BytesMut {
-        match msg {
-            Message::Open(open) => open.into(),
-        }
+        msg.encode(true)
     }
 }
 
 impl Message {
-    pub fn new_open(asnum: crate::types::ASNum, ip: std::net::Ipv4Addr) -> Self {
-        Self::Open(crate::packet::open::OpenMessage::new(asnum, ip))
+    pub fn new_open(
+        asnum: crate::types::ASNum,
+        ip: std::net::Ipv4Addr,
+        hold_time: crate::types::HoldTime,
+        capabilities: Vec<crate::packet::capability::Capability>,
+    ) -> Self {
+        Self::Open(crate::packet::open::OpenMessage::new(
+            asnum,
+            ip,
+            hold_time,
+            capabilities,
+        ))
+    }
+
+    pub fn new_update(
+        withdrawn_routes: Vec<crate::packet::prefix::Ipv4Prefix>,
+        path_attributes: Vec<crate::packet::attribute::PathAttribute>,
+        nlri: Vec<crate::packet::prefix::Ipv4Prefix>,
+    ) -> Self {
+        Self::Update(crate::packet::update::UpdateMessage::new(
+            withdrawn_routes,
+            path_attributes,
+            nlri,
+        ))
+    }
+
+    pub fn new_notification(
+        error_code: crate::packet::notification::ErrorCode,
+        data: &[u8],
+    ) -> Self {
+        Self::Notification(crate::packet::notification::NotificationMessage::new(
+            error_code, data,
+        ))
+    }
+
+    pub fn new_keepalive() -> Self {
+        Self::KeepAlive(crate::packet::keepalive::KeepaliveMessage::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_bytes_keepalive() {
+        let expected = Message::new_keepalive();
+        let bytes: bytes::BytesMut = expected.clone().into();
+        let message = Message::try_from(bytes).unwrap();
+
+        assert_eq!(message, expected);
+    }
+
+    #[test]
+    fn convert_bytes_notification() {
+        let expected = Message::new_notification(
+            crate::packet::notification::ErrorCode::Cease(
+                crate::packet::notification::CeaseSubcode::AdministrativeShutdown,
+            ),
+            &[],
+        );
+        let bytes: bytes::BytesMut = expected.clone().into();
+        let message = Message::try_from(bytes).unwrap();
+
+        assert_eq!(message, expected);
+    }
+
+    #[test]
+    fn random_messages() {
+        use rand::{Rng as _, SeedableRng as _};
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(4271);
+        for _ in 0..10000 {
+            let len = rng.random_range(crate::constants::HEADER_LEN..256);
+            let mut bytes = bytes::BytesMut::zeroed(len);
+            rng.fill(&mut bytes[crate::constants::HEADER_LEN..]);
+            bytes[..16].copy_from_slice(&[0xff; 16]);
+            bytes[16..18].copy_from_slice(&(len as u16).to_be_bytes());
+            bytes[18] = rng.random_range(1..=4);
+
+            check_decoded(bytes);
+        }
+    }
+
+    // a message is either rejected with the NOTIFICATION its type calls for or
+    // encoded to bytes that decode to the same message; the bytes themselves may
+    // differ since decoding normalizes things like host bits of prefixes
+    fn check_decoded(bytes: bytes::BytesMut) {
+        use crate::packet::notification::ErrorCode;
+
+        match Message::try_from(bytes.clone()) {
+            Ok(message) => assert_eq!(
+                Message::try_from(bytes::BytesMut::from(message.clone())).unwrap(),
+                message
+            ),
+            Err(err) if err.is_in_notification() => assert_eq!(bytes[18], 3),
+            Err(err) => {
+                let error_code = err.notification().error_code();
+                assert!(
+                    matches!(error_code, ErrorCode::MessageHeader(_))
+                        || matches!(
+                            (bytes.get(18), error_code),
+                            (Some(1), ErrorCode::OpenMessage(_))
+                                | (Some(2), ErrorCode::UpdateMessage(_))
+                        ),
+                    "unexpected error for {:?}: {:?}",
+                    bytes,
+                    error_code
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn truncated_messages() {
+        let messages = vec![
+            Message::new_open(
+                64512.into(),
+                "127.0.0.1".parse().unwrap(),
+                crate::types::HoldTime::new(),
+                vec![],
+            ),
+            Message::new_update(
+                vec![
+                    crate::packet::prefix::Ipv4Prefix::new("10.0.0.0".parse().unwrap(), 8).unwrap(),
+                ],
+                vec![
+                    crate::packet::attribute::PathAttribute::Origin(
+                        crate::packet::attribute::Origin::Igp,
+                    ),
+                    crate::packet::attribute::PathAttribute::AsPath(
+                        crate::packet::attribute::AsPath::new(vec![
+                            crate::packet::attribute::AsPathSegment::AsSequence(vec![64512.into()]),
+                        ]),
+                    ),
+                    crate::packet::attribute::PathAttribute::NextHop("10.0.0.1".parse().unwrap()),
+                ],
+                vec![
+                    crate::packet::prefix::Ipv4Prefix::new("10.1.0.0".parse().unwrap(), 16)
+                        .unwrap(),
+                ],
+            ),
+            Message::new_notification(
+                crate::packet::notification::ErrorCode::Cease(
+                    crate::packet::notification::CeaseSubcode::AdministrativeShutdown,
+                ),
+                &[],
+            ),
+            Message::new_keepalive(),
+        ];
+
+        for message in messages {
+            let bytes: bytes::BytesMut = message.into();
+            for len in 0..bytes.len() {
+                let mut truncated = bytes::BytesMut::from(&bytes[..len]);
+                if len >= crate::constants::HEADER_LEN {
+                    truncated[16..18].copy_from_slice(&(len as u16).to_be_bytes());
+                }
+
+                check_decoded(truncated);
+            }
+        }
     }
 }
@@ -1,3 +1,10 @@
-mod hdr;
+pub mod attribute;
+pub mod capability;
+pub mod codec;
+pub mod hdr;
+pub mod keepalive;
 pub mod message;
+pub mod notification;
 pub mod open;
+pub mod prefix;
+pub mod update;
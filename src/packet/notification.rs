@@ -0,0 +1,431 @@
+use bytes::BufMut as _;
+
+const MAX_DATA_LEN: usize = crate::constants::MAX_MESSAGE_LEN - crate::constants::HEADER_LEN - 2;
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct NotificationMessage {
+    hdr: crate::packet::hdr::Header,
+    error_code: ErrorCode,
+    data: bytes::BytesMut,
+}
+
+impl NotificationMessage {
+    // data that does not fit in a single message is truncated
+    pub fn new(error_code: ErrorCode, data: &[u8]) -> Self {
+        let data = &data[..data.len().min(MAX_DATA_LEN)];
+        Self {
+            hdr: crate::packet::hdr::Header::new(
+                (crate::constants::HEADER_LEN + 2 + data.len()) as u16,
+                crate::packet::hdr::MessageType::Notification,
+            ),
+            error_code,
+            data: bytes::BytesMut::from(data),
+        }
+    }
+
+    pub fn error_code(&self) -> &ErrorCode {
+        &self.error_code
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data[..]
+    }
+}
+
+impl TryFrom<bytes::BytesMut> for NotificationMessage {
+    type Error = crate::error::ConvertBytesErr;
+
+    fn try_from(value: bytes::BytesMut) -> Result<Self, Self::Error> {
+        if value.len() < crate::constants::HEADER_LEN + 2 {
+            return Err(Self::Error::new(
+                ErrorCode::MessageHeader(MessageHeaderErrorSubcode::BadMessageLength),
+                &(value.len() as u16).to_be_bytes(),
+                anyhow::anyhow!("notification message is too short: {}", value.len()),
+            ));
+        }
+
+        let hdr = crate::packet::hdr::Header::try_from(value.clone())?;
+        hdr.check_length(value.len())?;
+        let error_code = ErrorCode::from((
+            value[crate::constants::HEADER_LEN],
+            value[crate::constants::HEADER_LEN + 1],
+        ));
+        let data = bytes::BytesMut::from(&value[crate::constants::HEADER_LEN + 2..]);
+
+        Ok(Self {
+            hdr,
+            error_code,
+            data,
+        })
+    }
+}
+
+impl From<NotificationMessage> for bytes::BytesMut {
+    fn from(msg: NotificationMessage) -> bytes::BytesMut {
+        let mut bytes = bytes::BytesMut::new();
+        let hdr_bytes: bytes::BytesMut = msg.hdr.into();
+        let (code, subcode): (u8, u8) = msg.error_code.into();
+        bytes.put(hdr_bytes);
+        bytes.put_u8(code);
+        bytes.put_u8(subcode);
+        bytes.put(msg.data);
+
+        bytes
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum ErrorCode {
+    MessageHeader(MessageHeaderErrorSubcode),
+    OpenMessage(OpenMessageErrorSubcode),
+    UpdateMessage(UpdateMessageErrorSubcode),
+    HoldTimerExpired,
+    FiniteStateMachine,
+    Cease(CeaseSubcode),
+    // unknown codes, and subcodes of codes that define none, are kept as is
+    Other { code: u8, subcode: u8 },
+}
+
+impl From<(u8, u8)> for ErrorCode {
+    fn from((code, subcode): (u8, u8)) -> Self {
+        match (code, subcode) {
+            (1, _) => Self::MessageHeader(subcode.into()),
+            (2, _) => Self::OpenMessage(subcode.into()),
+            (3, _) => Self::UpdateMessage(subcode.into()),
+            (4, 0) => Self::HoldTimerExpired,
+            (5, 0) => Self::FiniteStateMachine,
+            (6, _) => Self::Cease(subcode.into()),
+            _ => Self::Other { code, subcode },
+        }
+    }
+}
+
+impl From<ErrorCode> for (u8, u8) {
+    fn from(ec: ErrorCode) -> (u8, u8) {
+        match ec {
+            ErrorCode::MessageHeader(subcode) => (1, subcode.into()),
+            ErrorCode::OpenMessage(subcode) => (2, subcode.into()),
+            ErrorCode::UpdateMessage(subcode) => (3, subcode.into()),
+            ErrorCode::HoldTimerExpired => (4, 0),
+            ErrorCode::FiniteStateMachine => (5, 0),
+            ErrorCode::Cease(subcode) => (6, subcode.into()),
+            ErrorCode::Other { code, subcode } => (code, subcode),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum MessageHeaderErrorSubcode {
+    Unspecific,
+    ConnectionNotSynchronized,
+    BadMessageLength,
+    BadMessageType,
+    Other(u8),
+}
+
+impl From<u8> for MessageHeaderErrorSubcode {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::Unspecific,
+            1 => Self::ConnectionNotSynchronized,
+            2 => Self::BadMessageLength,
+            3 => Self::BadMessageType,
+            _ => Self::Other(value),
+        }
+    }
+}
+
+impl From<MessageHeaderErrorSubcode> for u8 {
+    fn from(subcode: MessageHeaderErrorSubcode) -> u8 {
+        match subcode {
+            MessageHeaderErrorSubcode::Unspecific => 0,
+            MessageHeaderErrorSubcode::ConnectionNotSynchronized => 1,
+            MessageHeaderErrorSubcode::BadMessageLength => 2,
+            MessageHeaderErrorSubcode::BadMessageType => 3,
+            MessageHeaderErrorSubcode::Other(value) => value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum OpenMessageErrorSubcode {
+    Unspecific,
+    UnsupportedVersionNumber,
+    BadPeerAs,
+    BadBgpIdentifier,
+    UnsupportedOptionalParameter,
+    AuthenticationFailure,
+    UnacceptableHoldTime,
+    UnsupportedCapability,
+    Other(u8),
+}
+
+impl From<u8> for OpenMessageErrorSubcode {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::Unspecific,
+            1 => Self::UnsupportedVersionNumber,
+            2 => Self::BadPeerAs,
+            3 => Self::BadBgpIdentifier,
+            4 => Self::UnsupportedOptionalParameter,
+            5 => Self::AuthenticationFailure,
+            6 => Self::UnacceptableHoldTime,
+            7 => Self::UnsupportedCapability,
+            _ => Self::Other(value),
+        }
+    }
+}
+
+impl From<OpenMessageErrorSubcode> for u8 {
+    fn from(subcode: OpenMessageErrorSubcode) -> u8 {
+        match subcode {
+            OpenMessageErrorSubcode::Unspecific => 0,
+            OpenMessageErrorSubcode::UnsupportedVersionNumber => 1,
+            OpenMessageErrorSubcode::BadPeerAs => 2,
+            OpenMessageErrorSubcode::BadBgpIdentifier => 3,
+            OpenMessageErrorSubcode::UnsupportedOptionalParameter => 4,
+            OpenMessageErrorSubcode::AuthenticationFailure => 5,
+            OpenMessageErrorSubcode::UnacceptableHoldTime => 6,
+            OpenMessageErrorSubcode::UnsupportedCapability => 7,
+            OpenMessageErrorSubcode::Other(value) => value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum UpdateMessageErrorSubcode {
+    Unspecific,
+    MalformedAttributeList,
+    UnrecognizedWellKnownAttribute,
+    MissingWellKnownAttribute,
+    AttributeFlagsError,
+    AttributeLengthError,
+    InvalidOriginAttribute,
+    AsRoutingLoop,
+    InvalidNextHopAttribute,
+    OptionalAttributeError,
+    InvalidNetworkField,
+    MalformedAsPath,
+    Other(u8),
+}
+
+impl From<u8> for UpdateMessageErrorSubcode {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::Unspecific,
+            1 => Self::MalformedAttributeList,
+            2 => Self::UnrecognizedWellKnownAttribute,
+            3 => Self::MissingWellKnownAttribute,
+            4 => Self::AttributeFlagsError,
+            5 => Self::AttributeLengthError,
+            6 => Self::InvalidOriginAttribute,
+            7 => Self::AsRoutingLoop,
+            8 => Self::InvalidNextHopAttribute,
+            9 => Self::OptionalAttributeError,
+            10 => Self::InvalidNetworkField,
+            11 => Self::MalformedAsPath,
+            _ => Self::Other(value),
+        }
+    }
+}
+
+impl From<UpdateMessageErrorSubcode> for u8 {
+    fn from(subcode: UpdateMessageErrorSubcode) -> u8 {
+        match subcode {
+            UpdateMessageErrorSubcode::Unspecific => 0,
+            UpdateMessageErrorSubcode::MalformedAttributeList => 1,
+            UpdateMessageErrorSubcode::UnrecognizedWellKnownAttribute => 2,
+            UpdateMessageErrorSubcode::MissingWellKnownAttribute => 3,
+            UpdateMessageErrorSubcode::AttributeFlagsError => 4,
+            UpdateMessageErrorSubcode::AttributeLengthError => 5,
+            UpdateMessageErrorSubcode::InvalidOriginAttribute => 6,
+            UpdateMessageErrorSubcode::AsRoutingLoop => 7,
+            UpdateMessageErrorSubcode::InvalidNextHopAttribute => 8,
+            UpdateMessageErrorSubcode::OptionalAttributeError => 9,
+            UpdateMessageErrorSubcode::InvalidNetworkField => 10,
+            UpdateMessageErrorSubcode::MalformedAsPath => 11,
+            UpdateMessageErrorSubcode::Other(value) => value,
+        }
+    }
+}
+
+// Cease subcodes are defined by RFC 4486; anything else is kept as is.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum CeaseSubcode {
+    Unspecific,
+    MaximumNumberOfPrefixesReached,
+    AdministrativeShutdown,
+    PeerDeconfigured,
+    AdministrativeReset,
+    ConnectionRejected,
+    OtherConfigurationChange,
+    ConnectionCollisionResolution,
+    OutOfResources,
+    Other(u8),
+}
+
+impl From<u8> for CeaseSubcode {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::Unspecific,
+            1 => Self::MaximumNumberOfPrefixesReached,
+            2 => Self::AdministrativeShutdown,
+            3 => Self::PeerDeconfigured,
+            4 => Self::AdministrativeReset,
+            5 => Self::ConnectionRejected,
+            6 => Self::OtherConfigurationChange,
+            7 => Self::ConnectionCollisionResolution,
+            8 => Self::OutOfResources,
+            _ => Self::Other(value),
+        }
+    }
+}
+
+impl From<CeaseSubcode> for u8 {
+    fn from(subcode: CeaseSubcode) -> u8 {
+        match subcode {
+            CeaseSubcode::Unspecific => 0,
+            CeaseSubcode::MaximumNumberOfPrefixesReached => 1,
+            CeaseSubcode::AdministrativeShutdown => 2,
+            CeaseSubcode::PeerDeconfigured => 3,
+            CeaseSubcode::AdministrativeReset => 4,
+            CeaseSubcode::ConnectionRejected => 5,
+            CeaseSubcode::OtherConfigurationChange => 6,
+            CeaseSubcode::ConnectionCollisionResolution => 7,
+            CeaseSubcode::OutOfResources => 8,
+            CeaseSubcode::Other(value) => value,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_bytes_message() {
+        let expected = NotificationMessage::new(
+            ErrorCode::MessageHeader(MessageHeaderErrorSubcode::BadMessageLength),
+            &5000u16.to_be_bytes(),
+        );
+        let notification_message_bytes: bytes::BytesMut = expected.clone().into();
+        assert_eq!(notification_message_bytes.len(), 23);
+
+        let notification_message: NotificationMessage =
+            notification_message_bytes.try_into().unwrap();
+        assert_eq!(notification_message, expected);
+        assert_eq!(notification_message.data(), &5000u16.to_be_bytes());
+    }
+
+    #[test]
+    fn error_code_conversion() {
+        for (code, subcode, expected) in [
+            (
+                1,
+                1,
+                ErrorCode::MessageHeader(MessageHeaderErrorSubcode::ConnectionNotSynchronized),
+            ),
+            (
+                2,
+                6,
+                ErrorCode::OpenMessage(OpenMessageErrorSubcode::UnacceptableHoldTime),
+            ),
+            (
+                3,
+                11,
+                ErrorCode::UpdateMessage(UpdateMessageErrorSubcode::MalformedAsPath),
+            ),
+            (4, 0, ErrorCode::HoldTimerExpired),
+            (5, 0, ErrorCode::FiniteStateMachine),
+            (6, 0, ErrorCode::Cease(CeaseSubcode::Unspecific)),
+            (
+                6,
+                7,
+                ErrorCode::Cease(CeaseSubcode::ConnectionCollisionResolution),
+            ),
+        ] {
+            assert_eq!(ErrorCode::from((code, subcode)), expected);
+            assert_eq!(<(u8, u8)>::from(expected), (code, subcode));
+        }
+    }
+
+    #[test]
+    fn unknown_error_code() {
+        for (code, subcode, expected) in [
+            (
+                0,
+                0,
+                ErrorCode::Other {
+                    code: 0,
+                    subcode: 0,
+                },
+            ),
+            (
+                7,
+                1,
+                ErrorCode::Other {
+                    code: 7,
+                    subcode: 1,
+                },
+            ),
+            (
+                4,
+                1,
+                ErrorCode::Other {
+                    code: 4,
+                    subcode: 1,
+                },
+            ),
+            (
+                5,
+                3,
+                ErrorCode::Other {
+                    code: 5,
+                    subcode: 3,
+                },
+            ),
+            (
+                1,
+                4,
+                ErrorCode::MessageHeader(MessageHeaderErrorSubcode::Other(4)),
+            ),
+            (
+                2,
+                8,
+                ErrorCode::OpenMessage(OpenMessageErrorSubcode::Other(8)),
+            ),
+            (
+                3,
+                12,
+                ErrorCode::UpdateMessage(UpdateMessageErrorSubcode::Other(12)),
+            ),
+        ] {
+            assert_eq!(ErrorCode::from((code, subcode)), expected);
+            assert_eq!(<(u8, u8)>::from(expected), (code, subcode));
+        }
+
+        // unknown codes survive decoding and encoding unchanged
+        let mut bytes: bytes::BytesMut = NotificationMessage::new(
+            ErrorCode::Other {
+                code: 7,
+                subcode: 2,
+            },
+            &[1, 2],
+        )
+        .into();
+        let expected = bytes.clone();
+        let notification = NotificationMessage::try_from(bytes.split()).unwrap();
+        assert_eq!(bytes::BytesMut::from(notification), expected);
+    }
+
+    #[test]
+    fn data_is_truncated_to_message_size() {
+        let notification = NotificationMessage::new(ErrorCode::FiniteStateMachine, &[0; 5000]);
+        let bytes: bytes::BytesMut = notification.into();
+        assert_eq!(bytes.len(), crate::constants::MAX_MESSAGE_LEN);
+        assert_eq!(
+            u16::from_be_bytes([bytes[16], bytes[17]]) as usize,
+            crate::constants::MAX_MESSAGE_LEN
+        );
+    }
+}
@@ -1,67 +1,224 @@
-use anyhow::Context as _;
 use bytes::BufMut as _;
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct OpenMessage {
     hdr: crate::packet::hdr::Header,
     version: crate::types::Version,
-    asnum: crate::types::ASNum,
+    my_as: u16,
     hold_time: crate::types::HoldTime,
     bgp_id: std::net::Ipv4Addr,
-    opt_params: bytes::BytesMut,
-    opt_params_len: u8,
+    opt_params: Vec<OptionalParameter>,
 }
 
 impl OpenMessage {
-    pub fn new(asnum: crate::types::ASNum, bgp_id: std::net::Ipv4Addr) -> Self {
+    pub fn new(
+        asnum: crate::types::ASNum,
+        bgp_id: std::net::Ipv4Addr,
+        hold_time: crate::types::HoldTime,
+        capabilities: Vec<crate::packet::capability::Capability>,
+    ) -> Self {
+        let opt_params = if capabilities.is_empty() {
+            vec![]
+        } else {
+            vec![OptionalParameter::Capabilities(capabilities)]
+        };
+        let opt_params_len: usize = opt_params.iter().map(|p| p.encoded_len()).sum();
+
         Self {
-            hdr: crate::packet::hdr::Header::new(29, crate::packet::hdr::MessageType::Open),
+            hdr: crate::packet::hdr::Header::new(
+                (crate::constants::HEADER_LEN + 10 + opt_params_len) as u16,
+                crate::packet::hdr::MessageType::Open,
+            ),
             version: crate::types::Version::new(),
-            asnum,
-            hold_time: crate::types::HoldTime::new(),
+            my_as: asnum.to_two_octet(),
+            hold_time,
             bgp_id,
-            opt_params: bytes::BytesMut::new(),
-            opt_params_len: 0,
+            opt_params,
         }
     }
 }
 
+impl OpenMessage {
+    // four-octet AS numbers only fit in the capability, leaving AS_TRANS in `My AS`
+    pub fn asnum(&self) -> crate::types::ASNum {
+        self.capabilities()
+            .find_map(|capability| match capability {
+                crate::packet::capability::Capability::FourOctetAs(asn) => Some(*asn),
+                _ => None,
+            })
+            .unwrap_or_else(|| crate::types::ASNum::from(self.my_as as u32))
+    }
+
+    pub fn hold_time(&self) -> crate::types::HoldTime {
+        self.hold_time
+    }
+
+    pub fn bgp_id(&self) -> std::net::Ipv4Addr {
+        self.bgp_id
+    }
+
+    pub fn capabilities(&self) -> impl Iterator<Item = &crate::packet::capability::Capability> {
+        self.opt_params.iter().flat_map(|p| match p {
+            OptionalParameter::Capabilities(capabilities) => capabilities.iter(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum OptionalParameter {
+    Capabilities(Vec<crate::packet::capability::Capability>),
+}
+
+impl OptionalParameter {
+    const CAPABILITIES: u8 = 2;
+
+    fn encoded_len(&self) -> usize {
+        match self {
+            Self::Capabilities(capabilities) => {
+                2 + capabilities.iter().map(|c| c.encoded_len()).sum::<usize>()
+            }
+        }
+    }
+
+    fn encode(&self, bytes: &mut bytes::BytesMut) {
+        match self {
+            Self::Capabilities(capabilities) => {
+                bytes.put_u8(Self::CAPABILITIES);
+                bytes.put_u8((self.encoded_len() - 2) as u8);
+                for capability in capabilities {
+                    capability.encode(bytes);
+                }
+            }
+        }
+    }
+
+    fn decode_all(value: &[u8]) -> Result<Vec<Self>, crate::error::ConvertBytesErr> {
+        let mut opt_params = Vec::new();
+        let mut rest = value;
+        while !rest.is_empty() {
+            if rest.len() < 2 || rest.len() < 2 + rest[1] as usize {
+                return Err(crate::error::ConvertBytesErr::new(
+                    crate::packet::notification::ErrorCode::OpenMessage(
+                        crate::packet::notification::OpenMessageErrorSubcode::Unspecific,
+                    ),
+                    &[],
+                    anyhow::anyhow!("truncated optional parameter: {:?}", rest),
+                ));
+            }
+
+            let param_type = rest[0];
+            let body = &rest[2..2 + rest[1] as usize];
+            match param_type {
+                Self::CAPABILITIES => {
+                    let capabilities = crate::packet::capability::Capability::decode_all(body)
+                        .map_err(|e| {
+                            crate::error::ConvertBytesErr::new(
+                                crate::packet::notification::ErrorCode::OpenMessage(
+                                    crate::packet::notification::OpenMessageErrorSubcode::Unspecific,
+                                ),
+                                &[],
+                                e,
+                            )
+                        })?;
+                    opt_params.push(Self::Capabilities(capabilities));
+                }
+                _ => {
+                    return Err(crate::error::ConvertBytesErr::new(
+                        crate::packet::notification::ErrorCode::OpenMessage(
+                            crate::packet::notification::OpenMessageErrorSubcode::UnsupportedOptionalParameter,
+                        ),
+                        &[],
+                        anyhow::anyhow!("unsupported optional parameter: {param_type}"),
+                    ))
+                }
+            }
+            rest = &rest[2 + body.len()..];
+        }
+
+        Ok(opt_params)
+    }
+}
+
 impl TryFrom<bytes::BytesMut> for OpenMessage {
     type Error = crate::error::ConvertBytesErr;
 
     fn try_from(value: bytes::BytesMut) -> Result<Self, Self::Error> {
-        let header = crate::packet::hdr::Header::try_from(bytes::BytesMut::from(&value[0..19]))?;
-        let version = crate::types::Version::try_from(value[crate::constants::HEADER_LEN])?;
-        let asnum = crate::types::ASNum::from(u16::from_be_bytes([
+        let header = crate::packet::hdr::Header::try_from(value.clone())?;
+        header.check_length(value.len())?;
+        if value.len() < crate::constants::HEADER_LEN + 10 {
+            return Err(Self::Error::new(
+                crate::packet::notification::ErrorCode::MessageHeader(
+                    crate::packet::notification::MessageHeaderErrorSubcode::BadMessageLength,
+                ),
+                &(value.len() as u16).to_be_bytes(),
+                anyhow::anyhow!("open message is too short: {}", value.len()),
+            ));
+        }
+
+        let version =
+            crate::types::Version::try_from(value[crate::constants::HEADER_LEN]).map_err(|e| {
+                Self::Error::new(
+                    crate::packet::notification::ErrorCode::OpenMessage(
+                        crate::packet::notification::OpenMessageErrorSubcode::UnsupportedVersionNumber,
+                    ),
+                    &u16::from(u8::from(crate::types::Version::new())).to_be_bytes(),
+                    e,
+                )
+            })?;
+        let my_as = u16::from_be_bytes([
             value[crate::constants::HEADER_LEN + 1],
             value[crate::constants::HEADER_LEN + 2],
-        ]));
-        let hold_time = crate::types::HoldTime::from(u16::from_be_bytes([
+        ]);
+        let hold_time = crate::types::HoldTime::try_from(u16::from_be_bytes([
             value[crate::constants::HEADER_LEN + 3],
             value[crate::constants::HEADER_LEN + 4],
-        ]));
-        let tmp: [u8; 4] = value
-            [crate::constants::HEADER_LEN + 5..crate::constants::HEADER_LEN + 9]
-            .try_into()
-            .context(format!(
-                "invalid BGP ID: {:?}",
-                &value[crate::constants::HEADER_LEN + 5..crate::constants::HEADER_LEN + 9]
-            ))?;
-        let bgp_id = std::net::Ipv4Addr::from(tmp);
-        let opt_params_len = value[crate::constants::HEADER_LEN + 9];
-        let opt_params = bytes::BytesMut::from(
-            &value[crate::constants::HEADER_LEN + 10
-                ..crate::constants::HEADER_LEN + 10 + opt_params_len as usize],
+        ]))
+        .map_err(|e| {
+            Self::Error::new(
+                crate::packet::notification::ErrorCode::OpenMessage(
+                    crate::packet::notification::OpenMessageErrorSubcode::UnacceptableHoldTime,
+                ),
+                &[],
+                e,
+            )
+        })?;
+        let bgp_id = std::net::Ipv4Addr::new(
+            value[crate::constants::HEADER_LEN + 5],
+            value[crate::constants::HEADER_LEN + 6],
+            value[crate::constants::HEADER_LEN + 7],
+            value[crate::constants::HEADER_LEN + 8],
         );
+        if bgp_id.is_unspecified() || bgp_id.is_multicast() || bgp_id.is_broadcast() {
+            return Err(Self::Error::new(
+                crate::packet::notification::ErrorCode::OpenMessage(
+                    crate::packet::notification::OpenMessageErrorSubcode::BadBgpIdentifier,
+                ),
+                &[],
+                anyhow::anyhow!("invalid BGP ID: {bgp_id}"),
+            ));
+        }
+        let opt_params_len = value[crate::constants::HEADER_LEN + 9];
+        let opt_params = value
+            .get(crate::constants::HEADER_LEN + 10..)
+            .filter(|opt_params| opt_params.len() == opt_params_len as usize)
+            .ok_or_else(|| {
+                Self::Error::new(
+                    crate::packet::notification::ErrorCode::OpenMessage(
+                        crate::packet::notification::OpenMessageErrorSubcode::Unspecific,
+                    ),
+                    &[],
+                    anyhow::anyhow!("invalid optional parameters length: {opt_params_len}"),
+                )
+            })?;
+        let opt_params = OptionalParameter::decode_all(opt_params)?;
 
         Ok(Self {
             hdr: header,
             version,
-            asnum,
+            my_as,
             hold_time,
             bgp_id,
             opt_params,
-            opt_params_len,
         })
     }
 }
@@ -72,11 +229,14 @@ impl From<OpenMessage> for bytes::BytesMut {
         let hdr_bytes: bytes::BytesMut = msg.hdr.into();
         bytes.put(hdr_bytes);
         bytes.put_u8(msg.version.into());
-        bytes.put_u16(msg.asnum.into());
+        bytes.put_u16(msg.my_as);
         bytes.put_u16(msg.hold_time.into());
         bytes.put_u32(msg.bgp_id.into());
-        bytes.put_u8(msg.opt_params_len);
-        bytes.put(msg.opt_params);
+        let opt_params_len: usize = msg.opt_params.iter().map(|p| p.encoded_len()).sum();
+        bytes.put_u8(opt_params_len as u8);
+        for opt_param in &msg.opt_params {
+            opt_param.encode(&mut bytes);
+        }
 
         bytes
     }
@@ -88,10 +248,222 @@ mod tests {
 
     #[test]
     fn convert_bytes_message() {
-        let expected = OpenMessage::new(64512.into(), "127.0.0.1".parse().unwrap());
+        let expected = OpenMessage::new(
+            64512.into(),
+            "127.0.0.1".parse().unwrap(),
+            crate::types::HoldTime::new(),
+            vec![],
+        );
         let open_message_bytes: bytes::BytesMut = expected.clone().into();
         let open_message: OpenMessage = open_message_bytes.try_into().unwrap();
 
         assert_eq!(open_message, expected);
     }
+
+    #[test]
+    fn unsupported_version() {
+        let mut bytes: bytes::BytesMut = OpenMessage::new(
+            64512.into(),
+            "127.0.0.1".parse().unwrap(),
+            crate::types::HoldTime::new(),
+            vec![],
+        )
+        .into();
+        bytes[crate::constants::HEADER_LEN] = 3;
+        let err = OpenMessage::try_from(bytes).unwrap_err();
+
+        assert_eq!(
+            err.notification().error_code(),
+            &crate::packet::notification::ErrorCode::OpenMessage(
+                crate::packet::notification::OpenMessageErrorSubcode::UnsupportedVersionNumber
+            )
+        );
+        assert_eq!(err.notification().data(), &4u16.to_be_bytes());
+    }
+
+    #[test]
+    fn unacceptable_hold_time() {
+        let mut bytes: bytes::BytesMut = OpenMessage::new(
+            64512.into(),
+            "127.0.0.1".parse().unwrap(),
+            crate::types::HoldTime::new(),
+            vec![],
+        )
+        .into();
+        bytes[crate::constants::HEADER_LEN + 3] = 0;
+        bytes[crate::constants::HEADER_LEN + 4] = 2;
+        let err = OpenMessage::try_from(bytes).unwrap_err();
+
+        assert_eq!(
+            err.notification().error_code(),
+            &crate::packet::notification::ErrorCode::OpenMessage(
+                crate::packet::notification::OpenMessageErrorSubcode::UnacceptableHoldTime
+            )
+        );
+    }
+
+    #[test]
+    fn bad_bgp_identifier() {
+        let bytes: bytes::BytesMut = OpenMessage::new(
+            64512.into(),
+            "0.0.0.0".parse().unwrap(),
+            crate::types::HoldTime::new(),
+            vec![],
+        )
+        .into();
+        let err = OpenMessage::try_from(bytes).unwrap_err();
+
+        assert_eq!(
+            err.notification().error_code(),
+            &crate::packet::notification::ErrorCode::OpenMessage(
+                crate::packet::notification::OpenMessageErrorSubcode::BadBgpIdentifier
+            )
+        );
+    }
+
+    fn open_message_bytes() -> bytes::BytesMut {
+        OpenMessage::new(
+            64512.into(),
+            "127.0.0.1".parse().unwrap(),
+            crate::types::HoldTime::new(),
+            vec![],
+        )
+        .into()
+    }
+
+    #[test]
+    fn truncated_messages() {
+        let bytes = open_message_bytes();
+        for len in 0..bytes.len() {
+            let mut truncated = bytes::BytesMut::from(&bytes[..len]);
+            assert!(OpenMessage::try_from(truncated.clone()).is_err());
+
+            // the header length is kept consistent so that only the body is short
+            if len >= crate::constants::HEADER_LEN {
+                truncated[16..18].copy_from_slice(&(len as u16).to_be_bytes());
+                assert!(OpenMessage::try_from(truncated).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn oversized_opt_params_len() {
+        for opt_params_len in 1..=u8::MAX {
+            let mut bytes = open_message_bytes();
+            bytes[crate::constants::HEADER_LEN + 9] = opt_params_len;
+            let err = OpenMessage::try_from(bytes).unwrap_err();
+
+            assert_eq!(
+                err.notification().error_code(),
+                &crate::packet::notification::ErrorCode::OpenMessage(
+                    crate::packet::notification::OpenMessageErrorSubcode::Unspecific
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn inconsistent_header_length() {
+        for length in [0u16, 18, 28, 30, 4096, 4097, u16::MAX] {
+            let mut bytes = open_message_bytes();
+            bytes[16..18].copy_from_slice(&length.to_be_bytes());
+            let err = OpenMessage::try_from(bytes).unwrap_err();
+
+            assert_eq!(
+                err.notification().error_code(),
+                &crate::packet::notification::ErrorCode::MessageHeader(
+                    crate::packet::notification::MessageHeaderErrorSubcode::BadMessageLength
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn random_messages() {
+        use rand::{Rng as _, SeedableRng as _};
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(4271);
+        for _ in 0..10000 {
+            let mut bytes = open_message_bytes();
+            let len = rng.random_range(crate::constants::HEADER_LEN..bytes.len() + 64);
+            bytes.resize(len, 0);
+            rng.fill(&mut bytes[crate::constants::HEADER_LEN..]);
+            bytes[16..18].copy_from_slice(&(len as u16).to_be_bytes());
+
+            match OpenMessage::try_from(bytes.clone()) {
+                Ok(open) => assert_eq!(
+                    OpenMessage::try_from(bytes::BytesMut::from(open.clone())).unwrap(),
+                    open
+                ),
+                Err(err) => assert!(matches!(
+                    err.notification().error_code(),
+                    crate::packet::notification::ErrorCode::OpenMessage(_)
+                        | crate::packet::notification::ErrorCode::MessageHeader(_)
+                )),
+            }
+        }
+    }
+
+    #[test]
+    fn convert_bytes_message_with_capabilities() {
+        let capabilities = vec![
+            crate::packet::capability::Capability::Multiprotocol(
+                crate::types::Afi::Ipv4,
+                crate::types::Safi::Unicast,
+            ),
+            crate::packet::capability::Capability::Unknown {
+                code: 2,
+                value: bytes::BytesMut::new(),
+            },
+        ];
+        let expected = OpenMessage::new(
+            64512.into(),
+            "127.0.0.1".parse().unwrap(),
+            crate::types::HoldTime::new(),
+            capabilities.clone(),
+        );
+        let open_message_bytes: bytes::BytesMut = expected.clone().into();
+        assert_eq!(open_message_bytes.len(), 39);
+        let open_message: OpenMessage = open_message_bytes.try_into().unwrap();
+
+        assert_eq!(open_message, expected);
+        assert!(open_message.capabilities().eq(capabilities.iter()));
+    }
+
+    #[test]
+    fn unsupported_optional_parameter() {
+        let mut bytes = open_message_bytes();
+        bytes[crate::constants::HEADER_LEN + 9] = 2;
+        bytes.extend_from_slice(&[1, 0]);
+        let len = bytes.len() as u16;
+        bytes[16..18].copy_from_slice(&len.to_be_bytes());
+        let err = OpenMessage::try_from(bytes).unwrap_err();
+
+        assert_eq!(
+            err.notification().error_code(),
+            &crate::packet::notification::ErrorCode::OpenMessage(
+                crate::packet::notification::OpenMessageErrorSubcode::UnsupportedOptionalParameter
+            )
+        );
+    }
+
+    #[test]
+    fn four_octet_asnum() {
+        let bytes: bytes::BytesMut = OpenMessage::new(
+            4200000000.into(),
+            "127.0.0.1".parse().unwrap(),
+            crate::types::HoldTime::new(),
+            vec![crate::packet::capability::Capability::FourOctetAs(
+                4200000000.into(),
+            )],
+        )
+        .into();
+        assert_eq!(
+            &bytes[crate::constants::HEADER_LEN + 1..crate::constants::HEADER_LEN + 3],
+            &23456u16.to_be_bytes()
+        );
+
+        let open_message = OpenMessage::try_from(bytes).unwrap();
+        assert_eq!(open_message.asnum(), 4200000000.into());
+    }
 }
@@ -0,0 +1,275 @@
+use bytes::BufMut as _;
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Ipv4Prefix {
+    addr: std::net::Ipv4Addr,
+    len: u8,
+}
+
+impl Ipv4Prefix {
+    pub fn new(addr: std::net::Ipv4Addr, len: u8) -> anyhow::Result<Self> {
+        if len > 32 {
+            return Err(anyhow::anyhow!("invalid prefix length: {len}"));
+        }
+
+        let mask = u32::MAX.checked_shl(32 - len as u32).unwrap_or(0);
+        Ok(Self {
+            addr: std::net::Ipv4Addr::from(u32::from(addr) & mask),
+            len,
+        })
+    }
+
+    pub fn addr(&self) -> std::net::Ipv4Addr {
+        self.addr
+    }
+
+    pub fn prefix_len(&self) -> u8 {
+        self.len
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        1 + (self.len as usize).div_ceil(8)
+    }
+
+    pub fn encode(&self, bytes: &mut bytes::BytesMut) {
+        bytes.put_u8(self.len);
+        bytes.put(&self.addr.octets()[..(self.len as usize).div_ceil(8)]);
+    }
+
+    pub fn decode_all(value: &[u8]) -> anyhow::Result<Vec<Self>> {
+        let mut prefixes = Vec::new();
+        let mut rest = value;
+        while let Some((&len, tail)) = rest.split_first() {
+            let octets = (len as usize).div_ceil(8);
+            if len > 32 || tail.len() < octets {
+                return Err(anyhow::anyhow!("malformed prefix: {:?}", rest));
+            }
+
+            let mut addr = [0u8; 4];
+            addr[..octets].copy_from_slice(&tail[..octets]);
+            prefixes.push(Self::new(addr.into(), len)?);
+            rest = &tail[octets..];
+        }
+
+        Ok(prefixes)
+    }
+}
+
+impl std::fmt::Display for Ipv4Prefix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.addr, self.len)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Ipv6Prefix {
+    addr: std::net::Ipv6Addr,
+    len: u8,
+}
+
+impl Ipv6Prefix {
+    pub fn new(addr: std::net::Ipv6Addr, len: u8) -> anyhow::Result<Self> {
+        if len > 128 {
+            return Err(anyhow::anyhow!("invalid prefix length: {len}"));
+        }
+
+        let mask = u128::MAX.checked_shl(128 - len as u32).unwrap_or(0);
+        Ok(Self {
+            addr: std::net::Ipv6Addr::from(u128::from(addr) & mask),
+            len,
+        })
+    }
+
+    pub fn addr(&self) -> std::net::Ipv6Addr {
+        self.addr
+    }
+
+    pub fn prefix_len(&self) -> u8 {
+        self.len
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        1 + (self.len as usize).div_ceil(8)
+    }
+
+    pub fn encode(&self, bytes: &mut bytes::BytesMut) {
+        bytes.put_u8(self.len);
+        bytes.put(&self.addr.octets()[..(self.len as usize).div_ceil(8)]);
+    }
+
+    pub fn decode_all(value: &[u8]) -> anyhow::Result<Vec<Self>> {
+        let mut prefixes = Vec::new();
+        let mut rest = value;
+        while let Some((&len, tail)) = rest.split_first() {
+            let octets = (len as usize).div_ceil(8);
+            if len > 128 || tail.len() < octets {
+                return Err(anyhow::anyhow!("malformed prefix: {:?}", rest));
+            }
+
+            let mut addr = [0u8; 16];
+            addr[..octets].copy_from_slice(&tail[..octets]);
+            prefixes.push(Self::new(addr.into(), len)?);
+            rest = &tail[octets..];
+        }
+
+        Ok(prefixes)
+    }
+}
+
+impl std::fmt::Display for Ipv6Prefix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.addr, self.len)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Prefix {
+    V4(Ipv4Prefix),
+    V6(Ipv6Prefix),
+}
+
+impl Prefix {
+    pub fn afi(&self) -> crate::types::Afi {
+        match self {
+            Self::V4(_) => crate::types::Afi::Ipv4,
+            Self::V6(_) => crate::types::Afi::Ipv6,
+        }
+    }
+
+    pub fn encode(&self, bytes: &mut bytes::BytesMut) {
+        match self {
+            Self::V4(prefix) => prefix.encode(bytes),
+            Self::V6(prefix) => prefix.encode(bytes),
+        }
+    }
+
+    pub fn decode_all(afi: crate::types::Afi, value: &[u8]) -> anyhow::Result<Vec<Self>> {
+        match afi {
+            crate::types::Afi::Ipv4 => Ok(Ipv4Prefix::decode_all(value)?
+                .into_iter()
+                .map(Self::V4)
+                .collect()),
+            crate::types::Afi::Ipv6 => Ok(Ipv6Prefix::decode_all(value)?
+                .into_iter()
+                .map(Self::V6)
+                .collect()),
+        }
+    }
+}
+
+impl From<Ipv4Prefix> for Prefix {
+    fn from(prefix: Ipv4Prefix) -> Self {
+        Self::V4(prefix)
+    }
+}
+
+impl From<Ipv6Prefix> for Prefix {
+    fn from(prefix: Ipv6Prefix) -> Self {
+        Self::V6(prefix)
+    }
+}
+
+impl std::str::FromStr for Prefix {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, len) = s
+            .split_once('/')
+            .ok_or_else(|| anyhow::anyhow!("missing prefix length: {s}"))?;
+        let len: u8 = len.parse()?;
+        match addr.parse()? {
+            std::net::IpAddr::V4(addr) => Ok(Ipv4Prefix::new(addr, len)?.into()),
+            std::net::IpAddr::V6(addr) => Ok(Ipv6Prefix::new(addr, len)?.into()),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Prefix {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        <String as serde::Deserialize>::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl std::fmt::Display for Prefix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::V4(prefix) => prefix.fmt(f),
+            Self::V6(prefix) => prefix.fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_bytes_prefix() {
+        for len in 0..=32 {
+            let prefix = Ipv4Prefix::new("192.168.255.255".parse().unwrap(), len).unwrap();
+            let mut bytes = bytes::BytesMut::new();
+            prefix.encode(&mut bytes);
+            assert_eq!(bytes.len(), prefix.encoded_len());
+            assert_eq!(bytes.len(), 1 + (len as usize).div_ceil(8));
+
+            let prefixes = Ipv4Prefix::decode_all(&bytes).unwrap();
+            assert_eq!(prefixes, vec![prefix]);
+        }
+    }
+
+    #[test]
+    fn host_bits_are_masked() {
+        let prefix = Ipv4Prefix::new("10.1.2.3".parse().unwrap(), 8).unwrap();
+        assert_eq!(prefix.addr(), std::net::Ipv4Addr::new(10, 0, 0, 0));
+        assert_eq!(prefix.to_string(), "10.0.0.0/8");
+    }
+
+    #[test]
+    fn invalid_prefix() {
+        assert!(Ipv4Prefix::new("10.0.0.0".parse().unwrap(), 33).is_err());
+        assert!(Ipv4Prefix::decode_all(&[33, 10, 0, 0, 0, 0]).is_err());
+        assert!(Ipv4Prefix::decode_all(&[24, 10, 0]).is_err());
+    }
+
+    #[test]
+    fn convert_bytes_ipv6_prefix() {
+        for len in 0..=128 {
+            let prefix = Ipv6Prefix::new("2001:db8:ffff::ffff".parse().unwrap(), len).unwrap();
+            let mut bytes = bytes::BytesMut::new();
+            prefix.encode(&mut bytes);
+            assert_eq!(bytes.len(), prefix.encoded_len());
+
+            let prefixes = Prefix::decode_all(crate::types::Afi::Ipv6, &bytes).unwrap();
+            assert_eq!(prefixes, vec![Prefix::V6(prefix)]);
+        }
+    }
+
+    #[test]
+    fn invalid_ipv6_prefix() {
+        let prefix = Ipv6Prefix::new("2001:db8:1::1".parse().unwrap(), 32).unwrap();
+        assert_eq!(prefix.to_string(), "2001:db8::/32");
+        assert!(Ipv6Prefix::new("2001:db8::".parse().unwrap(), 129).is_err());
+        assert!(Ipv6Prefix::decode_all(&[129, 0x20, 0x01]).is_err());
+        assert!(Ipv6Prefix::decode_all(&[32, 0x20, 0x01]).is_err());
+    }
+
+    #[test]
+    fn prefix_can_parse() {
+        assert_eq!(
+            "10.100.210.1/24".parse::<Prefix>().unwrap(),
+            Prefix::V4(Ipv4Prefix::new("10.100.210.0".parse().unwrap(), 24).unwrap())
+        );
+        assert_eq!(
+            "2001:db8::/32".parse::<Prefix>().unwrap(),
+            Prefix::V6(Ipv6Prefix::new("2001:db8::".parse().unwrap(), 32).unwrap())
+        );
+        assert!("10.0.0.0".parse::<Prefix>().is_err());
+        assert!("10.0.0.0/33".parse::<Prefix>().is_err());
+        assert!("foo/8".parse::<Prefix>().is_err());
+    }
+}
@@ -0,0 +1,353 @@
+use bytes::BufMut as _;
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct UpdateMessage {
+    withdrawn_routes: Vec<crate::packet::prefix::Ipv4Prefix>,
+    path_attributes: Vec<crate::packet::attribute::PathAttribute>,
+    nlri: Vec<crate::packet::prefix::Ipv4Prefix>,
+}
+
+impl UpdateMessage {
+    pub fn new(
+        withdrawn_routes: Vec<crate::packet::prefix::Ipv4Prefix>,
+        path_attributes: Vec<crate::packet::attribute::PathAttribute>,
+        nlri: Vec<crate::packet::prefix::Ipv4Prefix>,
+    ) -> Self {
+        Self {
+            withdrawn_routes,
+            path_attributes,
+            nlri,
+        }
+    }
+
+    pub fn withdrawn_routes(&self) -> &[crate::packet::prefix::Ipv4Prefix] {
+        &self.withdrawn_routes
+    }
+
+    pub fn path_attributes(&self) -> &[crate::packet::attribute::PathAttribute] {
+        &self.path_attributes
+    }
+
+    pub fn nlri(&self) -> &[crate::packet::prefix::Ipv4Prefix] {
+        &self.nlri
+    }
+
+    // the encoding of AS numbers depends on whether both speakers support
+    // four-octet AS numbers, so the header length is only known when encoding
+    pub fn encode(&self, four_octet_as: bool) -> bytes::BytesMut {
+        let mut body = bytes::BytesMut::new();
+
+        let mut withdrawn_routes = bytes::BytesMut::new();
+        for prefix in &self.withdrawn_routes {
+            prefix.encode(&mut withdrawn_routes);
+        }
+        body.put_u16(withdrawn_routes.len() as u16);
+        body.put(withdrawn_routes);
+
+        let mut path_attributes = bytes::BytesMut::new();
+        crate::packet::attribute::PathAttribute::encode_all(
+            &self.path_attributes,
+            &mut path_attributes,
+            four_octet_as,
+        );
+        body.put_u16(path_attributes.len() as u16);
+        body.put(path_attributes);
+
+        for prefix in &self.nlri {
+            prefix.encode(&mut body);
+        }
+
+        let mut bytes: bytes::BytesMut = crate::packet::hdr::Header::new(
+            (crate::constants::HEADER_LEN + body.len()) as u16,
+            crate::packet::hdr::MessageType::Update,
+        )
+        .into();
+        bytes.put(body);
+        bytes
+    }
+
+    pub fn decode(
+        value: bytes::BytesMut,
+        four_octet_as: bool,
+    ) -> Result<Self, crate::error::ConvertBytesErr> {
+        let hdr = crate::packet::hdr::Header::try_from(value.clone())?;
+        hdr.check_length(value.len())?;
+        if value.len() < crate::constants::HEADER_LEN + 4 {
+            return Err(crate::error::ConvertBytesErr::new(
+                crate::packet::notification::ErrorCode::MessageHeader(
+                    crate::packet::notification::MessageHeaderErrorSubcode::BadMessageLength,
+                ),
+                &(value.len() as u16).to_be_bytes(),
+                anyhow::anyhow!("update message is too short: {}", value.len()),
+            ));
+        }
+
+        let malformed_attribute_list = |e| {
+            crate::error::ConvertBytesErr::new(
+                crate::packet::notification::ErrorCode::UpdateMessage(
+                    crate::packet::notification::UpdateMessageErrorSubcode::MalformedAttributeList,
+                ),
+                &[],
+                e,
+            )
+        };
+
+        let body = &value[crate::constants::HEADER_LEN..];
+        let withdrawn_routes_len = u16::from_be_bytes([body[0], body[1]]) as usize;
+        let body = &body[2..];
+        if body.len() < withdrawn_routes_len + 2 {
+            return Err(malformed_attribute_list(anyhow::anyhow!(
+                "invalid withdrawn routes length: {withdrawn_routes_len}"
+            )));
+        }
+        let withdrawn_routes =
+            crate::packet::prefix::Ipv4Prefix::decode_all(&body[..withdrawn_routes_len])
+                .map_err(malformed_attribute_list)?;
+
+        let body = &body[withdrawn_routes_len..];
+        let path_attributes_len = u16::from_be_bytes([body[0], body[1]]) as usize;
+        let body = &body[2..];
+        if body.len() < path_attributes_len {
+            return Err(malformed_attribute_list(anyhow::anyhow!(
+                "invalid total path attribute length: {path_attributes_len}"
+            )));
+        }
+        let path_attributes = crate::packet::attribute::PathAttribute::decode_all(
+            &body[..path_attributes_len],
+            four_octet_as,
+        )?;
+
+        let nlri = crate::packet::prefix::Ipv4Prefix::decode_all(&body[path_attributes_len..])
+            .map_err(|e| {
+                crate::error::ConvertBytesErr::new(
+                    crate::packet::notification::ErrorCode::UpdateMessage(
+                        crate::packet::notification::UpdateMessageErrorSubcode::InvalidNetworkField,
+                    ),
+                    &[],
+                    e,
+                )
+            })?;
+
+        // NEXT_HOP is only mandatory for IPv4 NLRI since MP_REACH_NLRI carries its own
+        let mut well_known = Vec::new();
+        let mp_reach = path_attributes.iter().any(|a| match a {
+            crate::packet::attribute::PathAttribute::MpReachNlri(mp_reach) => {
+                !mp_reach.nlri.is_empty()
+            }
+            _ => false,
+        });
+        if !nlri.is_empty() || mp_reach {
+            well_known.extend([
+                crate::packet::attribute::PathAttribute::ORIGIN,
+                crate::packet::attribute::PathAttribute::AS_PATH,
+            ]);
+        }
+        if !nlri.is_empty() {
+            well_known.push(crate::packet::attribute::PathAttribute::NEXT_HOP);
+        }
+        for type_code in well_known {
+            if !path_attributes.iter().any(|a| a.type_code() == type_code) {
+                return Err(crate::error::ConvertBytesErr::new(
+                    crate::packet::notification::ErrorCode::UpdateMessage(
+                        crate::packet::notification::UpdateMessageErrorSubcode::MissingWellKnownAttribute,
+                    ),
+                    &[type_code],
+                    anyhow::anyhow!("missing well-known attribute: {type_code}"),
+                ));
+            }
+        }
+
+        Ok(Self {
+            withdrawn_routes,
+            path_attributes,
+            nlri,
+        })
+    }
+}
+
+impl TryFrom<bytes::BytesMut> for UpdateMessage {
+    type Error = crate::error::ConvertBytesErr;
+
+    fn try_from(value: bytes::BytesMut) -> Result<Self, Self::Error> {
+        Self::decode(value, true)
+    }
+}
+
+impl From<UpdateMessage> for bytes::BytesMut {
+    fn from(msg: UpdateMessage) -> bytes::BytesMut {
+        msg.encode(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_bytes_message() {
+        let withdrawn_routes = (0..=32)
+            .map(|len| {
+                crate::packet::prefix::Ipv4Prefix::new("10.20.30.40".parse().unwrap(), len).unwrap()
+            })
+            .collect();
+        let nlri = (0..=32)
+            .map(|len| {
+                crate::packet::prefix::Ipv4Prefix::new("192.168.1.1".parse().unwrap(), len).unwrap()
+            })
+            .collect();
+        let path_attributes = vec![
+            crate::packet::attribute::PathAttribute::Origin(crate::packet::attribute::Origin::Igp),
+            crate::packet::attribute::PathAttribute::AsPath(crate::packet::attribute::AsPath::new(
+                vec![crate::packet::attribute::AsPathSegment::AsSequence(vec![
+                    64512.into(),
+                ])],
+            )),
+            crate::packet::attribute::PathAttribute::NextHop("10.0.0.1".parse().unwrap()),
+        ];
+
+        let expected = UpdateMessage::new(withdrawn_routes, path_attributes, nlri);
+        let update_message_bytes: bytes::BytesMut = expected.clone().into();
+        assert_eq!(
+            update_message_bytes.len(),
+            u16::from_be_bytes([update_message_bytes[16], update_message_bytes[17]]) as usize
+        );
+
+        let update_message: UpdateMessage = update_message_bytes.try_into().unwrap();
+        assert_eq!(update_message, expected);
+    }
+
+    #[test]
+    fn empty_update() {
+        let expected = UpdateMessage::new(vec![], vec![], vec![]);
+        let update_message_bytes: bytes::BytesMut = expected.clone().into();
+        assert_eq!(update_message_bytes.len(), 23);
+
+        let update_message: UpdateMessage = update_message_bytes.try_into().unwrap();
+        assert_eq!(update_message, expected);
+    }
+
+    #[test]
+    fn invalid_withdrawn_routes_length() {
+        let mut bytes: bytes::BytesMut = UpdateMessage::new(vec![], vec![], vec![]).into();
+        bytes[crate::constants::HEADER_LEN + 1] = 10;
+        let err = UpdateMessage::try_from(bytes).unwrap_err();
+
+        assert_eq!(
+            err.notification().error_code(),
+            &crate::packet::notification::ErrorCode::UpdateMessage(
+                crate::packet::notification::UpdateMessageErrorSubcode::MalformedAttributeList
+            )
+        );
+    }
+
+    #[test]
+    fn missing_well_known_attribute() {
+        let nlri =
+            vec![crate::packet::prefix::Ipv4Prefix::new("10.0.0.0".parse().unwrap(), 8).unwrap()];
+        let bytes: bytes::BytesMut = UpdateMessage::new(
+            vec![],
+            vec![crate::packet::attribute::PathAttribute::Origin(
+                crate::packet::attribute::Origin::Igp,
+            )],
+            nlri,
+        )
+        .into();
+        let err = UpdateMessage::try_from(bytes).unwrap_err();
+
+        assert_eq!(
+            err.notification().error_code(),
+            &crate::packet::notification::ErrorCode::UpdateMessage(
+                crate::packet::notification::UpdateMessageErrorSubcode::MissingWellKnownAttribute
+            )
+        );
+        assert_eq!(
+            err.notification().data(),
+            &[crate::packet::attribute::PathAttribute::AS_PATH]
+        );
+    }
+
+    #[test]
+    fn invalid_nlri() {
+        let mut bytes: bytes::BytesMut = UpdateMessage::new(vec![], vec![], vec![]).into();
+        bytes.extend_from_slice(&[33, 10, 0, 0, 0, 0]);
+        let length = bytes.len() as u16;
+        bytes[16..18].copy_from_slice(&length.to_be_bytes());
+        let err = UpdateMessage::try_from(bytes).unwrap_err();
+
+        assert_eq!(
+            err.notification().error_code(),
+            &crate::packet::notification::ErrorCode::UpdateMessage(
+                crate::packet::notification::UpdateMessageErrorSubcode::InvalidNetworkField
+            )
+        );
+    }
+
+    #[test]
+    fn convert_bytes_message_two_octet() {
+        let nlri =
+            vec![crate::packet::prefix::Ipv4Prefix::new("10.0.0.0".parse().unwrap(), 8).unwrap()];
+        let path_attributes = vec![
+            crate::packet::attribute::PathAttribute::Origin(crate::packet::attribute::Origin::Igp),
+            crate::packet::attribute::PathAttribute::AsPath(crate::packet::attribute::AsPath::new(
+                vec![crate::packet::attribute::AsPathSegment::AsSequence(vec![
+                    4200000000.into(),
+                ])],
+            )),
+            crate::packet::attribute::PathAttribute::NextHop("10.0.0.1".parse().unwrap()),
+        ];
+
+        let expected = UpdateMessage::new(vec![], path_attributes, nlri);
+        let update_message_bytes = expected.encode(false);
+        assert_eq!(
+            update_message_bytes.len(),
+            u16::from_be_bytes([update_message_bytes[16], update_message_bytes[17]]) as usize
+        );
+
+        let update_message = UpdateMessage::decode(update_message_bytes, false).unwrap();
+        assert_eq!(update_message, expected);
+    }
+
+    #[test]
+    fn mp_reach_nlri_requires_origin_and_as_path() {
+        let mp_reach = crate::packet::attribute::PathAttribute::MpReachNlri(
+            crate::packet::attribute::MpReachNlri {
+                afi: crate::types::Afi::Ipv6,
+                safi: crate::types::Safi::Unicast,
+                next_hop: crate::packet::attribute::NextHop::V6 {
+                    global: "2001:db8::1".parse().unwrap(),
+                    link_local: None,
+                },
+                nlri: vec![crate::packet::prefix::Ipv6Prefix::new(
+                    "2001:db8:1::".parse().unwrap(),
+                    48,
+                )
+                .unwrap()
+                .into()],
+            },
+        );
+        let expected = UpdateMessage::new(
+            vec![],
+            vec![
+                crate::packet::attribute::PathAttribute::Origin(
+                    crate::packet::attribute::Origin::Igp,
+                ),
+                crate::packet::attribute::PathAttribute::AsPath(
+                    crate::packet::attribute::AsPath::default(),
+                ),
+                mp_reach.clone(),
+            ],
+            vec![],
+        );
+        let bytes: bytes::BytesMut = expected.clone().into();
+        assert_eq!(UpdateMessage::try_from(bytes).unwrap(), expected);
+
+        let bytes: bytes::BytesMut = UpdateMessage::new(vec![], vec![mp_reach], vec![]).into();
+        let err = UpdateMessage::try_from(bytes).unwrap_err();
+        assert_eq!(
+            err.notification().error_code(),
+            &crate::packet::notification::ErrorCode::UpdateMessage(
+                crate::packet::notification::UpdateMessageErrorSubcode::MissingWellKnownAttribute
+            )
+        );
+    }
+}
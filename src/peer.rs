@@ -1,20 +1,99 @@
-use crate::packet::message;
+type ConnectTask<T> =
+    tokio::task::JoinHandle<Result<crate::connection::Connection<T>, crate::error::ConnectionErr>>;
+
+#[derive(Debug, Clone)]
+pub struct PeerHandle {
+    commands: tokio::sync::mpsc::UnboundedSender<crate::event::Event>,
+}
+
+impl PeerHandle {
+    pub fn start(&self) {
+        self.send(crate::event::Event::Start);
+    }
+
+    pub fn stop(&self) {
+        self.send(crate::event::Event::Stop);
+    }
+
+    fn send(&self, event: crate::event::Event) {
+        if self.commands.send(event).is_err() {
+            tracing::warn!("peer is no longer running");
+        }
+    }
+}
 
 #[derive(Debug)]
-pub struct Peer {
+pub struct Peer<C: crate::transport::Connector = crate::transport::TcpConnector> {
     state: crate::state::State,
     queue: crate::queue::Queue,
     config: crate::config::Config,
-    connection: Option<crate::connection::Connection>,
+    connector: C,
+    connection: Option<crate::connection::Connection<C::Transport>>,
+    connect_task: Option<ConnectTask<C::Transport>>,
+    inbound: Option<tokio::sync::mpsc::UnboundedReceiver<C::Transport>>,
+    inbound_connection: Option<crate::connection::Connection<C::Transport>>,
+    // a second connection to the same peer waiting for collision resolution
+    collision: Option<crate::connection::Connection<C::Transport>>,
+    commands_tx: tokio::sync::mpsc::UnboundedSender<crate::event::Event>,
+    commands_rx: tokio::sync::mpsc::UnboundedReceiver<crate::event::Event>,
+    hold_time: crate::types::HoldTime,
+    negotiated_capabilities: Vec<crate::packet::capability::Capability>,
+    remote_router_id: std::net::Ipv4Addr,
+    adj_rib_in: crate::rib::AdjRibIn,
+    adj_rib_out: crate::rib::AdjRibOut,
+    loc_rib: crate::rib::SharedLocRib,
+    loc_rib_changes: tokio::sync::watch::Receiver<()>,
+    connect_retry_counter: u32,
+    connect_retry_timer: crate::timer::Timer,
+    hold_timer: crate::timer::Timer,
+    keepalive_timer: crate::timer::Timer,
+    idle_hold_timer: crate::timer::Timer,
 }
 
 impl Peer {
     pub fn new(config: crate::config::Config) -> Self {
+        Self::with_loc_rib(config, crate::rib::LocRib::shared())
+    }
+
+    // peers of the same speaker share a single Loc-RIB
+    pub fn with_loc_rib(config: crate::config::Config, loc_rib: crate::rib::SharedLocRib) -> Self {
+        Self::with_connector(config, loc_rib, crate::transport::TcpConnector)
+    }
+}
+
+impl<C: crate::transport::Connector> Peer<C> {
+    // the connector decides what transport the session runs over
+    pub fn with_connector(
+        config: crate::config::Config,
+        loc_rib: crate::rib::SharedLocRib,
+        connector: C,
+    ) -> Self {
+        let (commands_tx, commands_rx) = tokio::sync::mpsc::unbounded_channel();
+        let loc_rib_changes = loc_rib.lock().unwrap().subscribe();
         Peer {
             state: crate::state::State::Idle,
             queue: crate::queue::Queue::new(),
+            hold_time: config.hold_time,
+            negotiated_capabilities: vec![],
+            remote_router_id: std::net::Ipv4Addr::UNSPECIFIED,
+            adj_rib_in: crate::rib::AdjRibIn::new(),
+            adj_rib_out: crate::rib::AdjRibOut::new(),
+            loc_rib,
+            loc_rib_changes,
             config,
+            connector,
             connection: None,
+            connect_task: None,
+            inbound: None,
+            inbound_connection: None,
+            collision: None,
+            commands_tx,
+            commands_rx,
+            connect_retry_counter: 0,
+            connect_retry_timer: crate::timer::Timer::new(),
+            hold_timer: crate::timer::Timer::new(),
+            keepalive_timer: crate::timer::Timer::new(),
+            idle_hold_timer: crate::timer::Timer::new(),
         }
     }
 
@@ -24,61 +103,777 @@ impl Peer {
         self.queue.enqueue(crate::event::Event::Start);
     }
 
+    #[tracing::instrument]
+    pub fn stop(&mut self) {
+        tracing::info!("stopping peer");
+        self.queue.enqueue(crate::event::Event::Stop);
+    }
+
+    // inbound connections are then taken from the shared listener, which passive
+    // peers use instead of binding their own
+    pub fn listen<A: crate::transport::Acceptor<Transport = C::Transport>>(
+        &mut self,
+        listener: &A,
+    ) {
+        self.inbound = Some(listener.register(self.config.remote_ip));
+    }
+
+    pub fn state(&self) -> crate::state::State {
+        self.state.clone()
+    }
+
+    pub fn handle(&self) -> PeerHandle {
+        PeerHandle {
+            commands: self.commands_tx.clone(),
+        }
+    }
+
+    pub async fn run(&mut self) {
+        loop {
+            self.next().await;
+        }
+    }
+
     #[tracing::instrument]
     pub async fn next(&mut self) {
-        if let Some(event) = self.queue.dequeue() {
-            tracing::info!("processing event: {:?}", event);
-
-            match self.state {
-                crate::state::State::Idle => {
-                    if event == crate::event::Event::Start {
-                        self.connection = crate::connection::Connection::connect(&self.config)
-                            .await
-                            .ok();
-
-                        if self.connection.is_some() {
-                            self.queue.enqueue(crate::event::Event::TcpConnect);
-                        } else {
-                            panic!("failed to connect: {:?}", self.config);
+        let event = match self.queue.dequeue() {
+            Some(event) => event,
+            None => self.wait_event().await,
+        };
+
+        tracing::info!("processing event: {:?}", event);
+        self.handle_event(event).await;
+    }
+
+    async fn wait_event(&mut self) -> crate::event::Event {
+        tokio::select! {
+            Some(event) = self.commands_rx.recv() => event,
+            result = Self::wait_connect(&mut self.connect_task) => {
+                self.connect_task = None;
+                match result {
+                    Ok(connection) => {
+                        self.connection = Some(connection);
+                        crate::event::Event::TcpConnect
+                    }
+                    Err(e) => {
+                        tracing::warn!("failed to connect: {:?}", e);
+                        crate::event::Event::TcpConnectionFails
+                    }
+                }
+            }
+            Some(stream) = Self::wait_inbound(&mut self.inbound) => {
+                self.inbound_connection = Some(crate::connection::Connection::incoming(stream));
+                crate::event::Event::TcpConnectionConfirmed
+            }
+            result = Self::wait_message(&mut self.collision) => match result {
+                Ok(crate::packet::message::Message::Open(open)) => {
+                    crate::event::Event::CollisionOpen(open)
+                }
+                _ => crate::event::Event::CollisionFails,
+            },
+            result = Self::wait_message(&mut self.connection) => match result {
+                Ok(message) => {
+                    tracing::info!("received message: {:?}", message);
+                    crate::event::Event::from(message)
+                }
+                Err(crate::error::CodecErr::Message(e)) => {
+                    tracing::warn!("received malformed message: {:?}", e);
+                    crate::event::Event::from(e)
+                }
+                Err(crate::error::CodecErr::Io(e)) => {
+                    tracing::warn!("connection failed: {:?}", e);
+                    crate::event::Event::TcpConnectionFails
+                }
+            },
+            Ok(()) = self.loc_rib_changes.changed() => crate::event::Event::LocRibChanged,
+            _ = self.connect_retry_timer.expired() => {
+                self.connect_retry_timer.stop();
+                crate::event::Event::ConnectRetryTimerExpires
+            }
+            _ = self.hold_timer.expired() => {
+                self.hold_timer.stop();
+                crate::event::Event::HoldTimerExpires
+            }
+            _ = self.keepalive_timer.expired() => {
+                self.keepalive_timer.stop();
+                crate::event::Event::KeepaliveTimerExpires
+            }
+            _ = self.idle_hold_timer.expired() => {
+                self.idle_hold_timer.stop();
+                crate::event::Event::IdleHoldTimerExpires
+            }
+        }
+    }
+
+    async fn wait_connect(
+        connect_task: &mut Option<ConnectTask<C::Transport>>,
+    ) -> Result<crate::connection::Connection<C::Transport>, crate::error::ConnectionErr> {
+        match connect_task {
+            Some(connect_task) => connect_task
+                .await
+                .map_err(|e| crate::error::ConnectionErr::from(anyhow::Error::from(e)))?,
+            None => std::future::pending().await,
+        }
+    }
+
+    async fn wait_inbound(
+        inbound: &mut Option<tokio::sync::mpsc::UnboundedReceiver<C::Transport>>,
+    ) -> Option<C::Transport> {
+        match inbound {
+            Some(inbound) => inbound.recv().await,
+            None => std::future::pending().await,
+        }
+    }
+
+    async fn wait_message(
+        connection: &mut Option<crate::connection::Connection<C::Transport>>,
+    ) -> Result<crate::packet::message::Message, crate::error::CodecErr> {
+        match connection {
+            Some(connection) => connection.get_message().await,
+            None => std::future::pending().await,
+        }
+    }
+
+    async fn handle_event(&mut self, event: crate::event::Event) {
+        use crate::event::Event;
+        use crate::state::State;
+
+        // events of the Loc-RIB and of a colliding connection are not FSM transitions
+        match &event {
+            // Loc-RIB changes only matter once routes can be advertised
+            Event::LocRibChanged => {
+                if self.state == State::Established {
+                    self.sync_adj_rib_out().await;
+                }
+                return;
+            }
+            Event::CollisionOpen(open) => {
+                if !self.resolve_collision(open.bgp_id()).await {
+                    self.queue.enqueue(Event::BgpOpen(open.clone()));
+                }
+                return;
+            }
+            Event::CollisionFails => {
+                self.collision = None;
+                return;
+            }
+            _ => {}
+        }
+
+        match self.state {
+            State::Idle => {
+                if event == Event::Start || event == Event::IdleHoldTimerExpires {
+                    if event == Event::Start {
+                        self.connect_retry_counter = 0;
+                    }
+                    self.idle_hold_timer.stop();
+                    self.start_connect_retry_timer();
+                    self.state = match self.config.mode {
+                        crate::config::Mode::Active => State::Connect,
+                        crate::config::Mode::Passive => State::Active,
+                    };
+                    self.open_connection();
+                } else if event == Event::TcpConnectionConfirmed {
+                    self.reject_connection();
+                }
+            }
+            State::Connect => match event {
+                Event::Stop => self.stop_session(),
+                Event::ConnectRetryTimerExpires => {
+                    self.close_connection();
+                    self.start_connect_retry_timer();
+                    self.open_connection();
+                }
+                Event::TcpConnect => self.send_open().await,
+                Event::TcpConnectionConfirmed => self.accept_connection().await,
+                Event::TcpConnectionFails => {
+                    // counted so that consecutive failed attempts back off
+                    self.connect_retry_counter += 1;
+                    self.close_connection();
+                    self.start_connect_retry_timer();
+                    self.state = State::Active;
+                }
+                // starting a running session is ignored (RFC 4271 8.2.2)
+                Event::Start | Event::IdleHoldTimerExpires => {}
+                _ => self.drop_to_idle(),
+            },
+            State::Active => match event {
+                Event::Stop => self.stop_session(),
+                Event::ConnectRetryTimerExpires => {
+                    self.start_connect_retry_timer();
+                    self.state = State::Connect;
+                    self.open_connection();
+                }
+                Event::TcpConnect => self.send_open().await,
+                Event::TcpConnectionConfirmed => self.accept_connection().await,
+                Event::Start | Event::IdleHoldTimerExpires => {}
+                _ => self.drop_to_idle(),
+            },
+            State::OpenSent => match event {
+                Event::Stop => self.cease().await,
+                Event::TcpConnectionConfirmed => self.open_collision().await,
+                Event::HoldTimerExpires => self.hold_timer_expired().await,
+                Event::TcpConnectionFails => {
+                    self.close_connection();
+                    self.hold_timer.stop();
+                    self.start_connect_retry_timer();
+                    self.state = State::Active;
+                }
+                Event::BgpOpen(open) => {
+                    if !self.resolve_collision(open.bgp_id()).await {
+                        // the OPEN is awaited on the surviving connection
+                        return;
+                    }
+                    if open.asnum() != self.config.remote_as {
+                        tracing::warn!("unexpected peer AS: {:?}", open.asnum());
+                        self.drop_to_idle_with(
+                            crate::packet::notification::NotificationMessage::new(
+                                crate::packet::notification::ErrorCode::OpenMessage(
+                                    crate::packet::notification::OpenMessageErrorSubcode::BadPeerAs,
+                                ),
+                                &[],
+                            ),
+                        )
+                        .await;
+                        return;
+                    }
+
+                    let remote_capabilities: Vec<_> = open.capabilities().collect();
+                    let mut missing_capabilities = bytes::BytesMut::new();
+                    for capability in &self.config.required_capabilities {
+                        if !remote_capabilities
+                            .iter()
+                            .any(|c| capability.is_compatible(c))
+                        {
+                            capability.encode(&mut missing_capabilities);
                         }
-                        self.state = crate::state::State::Connect;
                     }
+                    if !missing_capabilities.is_empty() {
+                        tracing::warn!("peer lacks required capabilities");
+                        self.drop_to_idle_with(
+                            crate::packet::notification::NotificationMessage::new(
+                                crate::packet::notification::ErrorCode::OpenMessage(
+                                    crate::packet::notification::OpenMessageErrorSubcode::UnsupportedCapability,
+                                ),
+                                &missing_capabilities,
+                            ),
+                        )
+                        .await;
+                        return;
+                    }
+                    let local_capabilities = self.local_capabilities();
+                    self.negotiated_capabilities = remote_capabilities
+                        .into_iter()
+                        .filter(|capability| {
+                            local_capabilities
+                                .iter()
+                                .any(|c| c.is_compatible(capability))
+                        })
+                        .cloned()
+                        .collect();
+                    let four_octet_as = self.negotiated_capabilities.iter().any(|capability| {
+                        matches!(
+                            capability,
+                            crate::packet::capability::Capability::FourOctetAs(_)
+                        )
+                    });
+                    if let Some(connection) = self.connection.as_mut() {
+                        connection.set_four_octet_as(four_octet_as);
+                    }
+                    tracing::info!(
+                        "negotiated capabilities: {:?}",
+                        self.negotiated_capabilities
+                    );
+
+                    self.remote_router_id = open.bgp_id();
+                    self.hold_time = std::cmp::min(self.config.hold_time, open.hold_time());
+                    tracing::info!("negotiated hold time: {:?}", self.hold_time);
+
+                    self.connect_retry_timer.stop();
+                    self.send(crate::packet::message::Message::new_keepalive())
+                        .await;
+                    self.start_hold_timer();
+                    self.start_keepalive_timer();
+                    self.state = State::OpenConfirm;
                 }
-                crate::state::State::Connect => {
-                    if event == crate::event::Event::TcpConnect {
-                        self.connection
-                            .as_mut()
-                            .expect("connection is none")
-                            .send(crate::packet::message::Message::new_open(
-                                self.config.local_as,
-                                self.config.local_ip,
-                            ))
-                            .await;
-                        self.state = crate::state::State::OpenSent;
+                Event::BgpHeaderErr(notification) | Event::BgpOpenMsgErr(notification) => {
+                    self.drop_to_idle_with(notification).await
+                }
+                Event::NotifMsg(_) | Event::NotifMsgErr => self.drop_to_idle(),
+                Event::Start | Event::IdleHoldTimerExpires => {}
+                _ => self.fsm_error().await,
+            },
+            State::OpenConfirm => match event {
+                Event::Stop => self.cease().await,
+                Event::TcpConnectionConfirmed => {
+                    self.collision = self.inbound_connection.take();
+                    if !self.resolve_collision(self.remote_router_id).await {
+                        self.keepalive_timer.stop();
+                        self.send_open().await;
                     }
                 }
-                crate::state::State::OpenSent => {
-                    if let crate::event::Event::BgpOpen(_) = event {
-                        self.state = crate::state::State::OpenConfirm;
+                Event::HoldTimerExpires => self.hold_timer_expired().await,
+                Event::KeepaliveTimerExpires => {
+                    self.send(crate::packet::message::Message::new_keepalive())
+                        .await;
+                    self.start_keepalive_timer();
+                }
+                Event::KeepAliveMsg => {
+                    self.start_hold_timer();
+                    self.connect_retry_counter = 0;
+                    self.state = State::Established;
+                    self.sync_adj_rib_out().await;
+                }
+                Event::BgpHeaderErr(notification) | Event::BgpOpenMsgErr(notification) => {
+                    self.drop_to_idle_with(notification).await
+                }
+                Event::TcpConnectionFails | Event::NotifMsg(_) | Event::NotifMsgErr => {
+                    self.drop_to_idle()
+                }
+                Event::Start | Event::IdleHoldTimerExpires => {}
+                _ => self.fsm_error().await,
+            },
+            State::Established => match event {
+                Event::Stop => self.cease().await,
+                // an established session always wins a collision
+                Event::TcpConnectionConfirmed => {
+                    if let Some(connection) = self.inbound_connection.take() {
+                        Self::close_collided(connection).await;
                     }
                 }
-                _ => {
-                    tracing::error!("unhandled state: {:?}", self.state);
+                Event::HoldTimerExpires => self.hold_timer_expired().await,
+                Event::KeepaliveTimerExpires => {
+                    self.send(crate::packet::message::Message::new_keepalive())
+                        .await;
+                    self.start_keepalive_timer();
+                }
+                Event::KeepAliveMsg => self.start_hold_timer(),
+                Event::UpdateMsg(update) => {
+                    self.start_hold_timer();
+                    self.process_update(&update);
+                }
+                Event::BgpHeaderErr(notification)
+                | Event::BgpOpenMsgErr(notification)
+                | Event::UpdateMsgErr(notification) => self.drop_to_idle_with(notification).await,
+                Event::TcpConnectionFails | Event::NotifMsg(_) | Event::NotifMsgErr => {
+                    self.drop_to_idle()
+                }
+                Event::Start | Event::IdleHoldTimerExpires => {}
+                _ => self.fsm_error().await,
+            },
+        }
+    }
+
+    fn process_update(&mut self, update: &crate::packet::update::UpdateMessage) {
+        let source = crate::rib::Source {
+            address: self.config.remote_ip,
+            router_id: self.remote_router_id,
+            ebgp: self.config.remote_as != self.config.local_as,
+        };
+        let mut loc_rib = self.loc_rib.lock().unwrap();
+        for prefix in crate::route::Route::withdrawn(update) {
+            if self.adj_rib_in.withdraw(&prefix).is_some() {
+                loc_rib.withdraw(self.config.remote_ip, &prefix);
+            }
+        }
+        for mut route in crate::route::Route::announced(update) {
+            let ignored = if !self.afi_negotiated(route.afi()) {
+                tracing::warn!("ignoring route for unnegotiated AFI: {}", route.prefix);
+                true
+            } else if route
+                .as_path()
+                .is_some_and(|as_path| as_path.contains(self.config.local_as))
+            {
+                tracing::debug!("ignoring looped route: {}", route.prefix);
+                true
+            } else {
+                false
+            };
+            if ignored {
+                // an ignored route still replaces the one announced before for the prefix
+                if self.adj_rib_in.withdraw(&route.prefix).is_some() {
+                    loc_rib.withdraw(self.config.remote_ip, &route.prefix);
                 }
+                continue;
             }
+            // LOCAL_PREF from another AS is not ours to follow (RFC 4271 5.1.5)
+            if source.ebgp {
+                route.path_attributes.retain(|attribute| {
+                    !matches!(
+                        attribute,
+                        crate::packet::attribute::PathAttribute::LocalPref(_)
+                    )
+                });
+            }
+            loc_rib.insert(crate::rib::Candidate::new(source, route.clone()));
+            self.adj_rib_in.insert(route);
+        }
+    }
 
-            if let Some(connection) = &mut self.connection {
-                if let Some(msg) = connection.get_message().await {
-                    tracing::info!("received message: {:?}", msg);
-                    match msg {
-                        message::Message::Open(open) => {
-                            self.queue.enqueue(crate::event::Event::BgpOpen(open));
-                        }
-                    }
+    // advertises the difference between the exportable best routes and Adj-RIB-Out
+    async fn sync_adj_rib_out(&mut self) {
+        let routes: std::collections::HashMap<_, _> = {
+            let loc_rib = self.loc_rib.lock().unwrap();
+            loc_rib
+                .best_candidates()
+                .filter_map(|candidate| self.export(candidate))
+                .map(|route| (route.prefix, route))
+                .collect()
+        };
+
+        let withdrawn: Vec<_> = self
+            .adj_rib_out
+            .table()
+            .iter()
+            .map(|route| route.prefix)
+            .filter(|prefix| !routes.contains_key(prefix))
+            .collect();
+        for prefix in withdrawn {
+            self.adj_rib_out.withdraw(&prefix);
+            self.send(crate::packet::message::Message::Update(
+                crate::route::Route::withdrawal(prefix),
+            ))
+            .await;
+        }
+
+        for route in routes.into_values() {
+            if self.adj_rib_out.table().get(&route.prefix) == Some(&route) {
+                continue;
+            }
+            self.send(crate::packet::message::Message::Update(route.to_update()))
+                .await;
+            self.adj_rib_out.insert(route);
+        }
+    }
+
+    // applies the RFC 4271 section 9.1.3 rules to a best route, returning the route
+    // as advertised to this peer
+    fn export(&self, candidate: &crate::rib::Candidate) -> Option<crate::route::Route> {
+        let ebgp = self.config.remote_as != self.config.local_as;
+        if candidate.source.address == self.config.remote_ip
+            || (!ebgp && !candidate.source.ebgp && !candidate.source.is_local())
+            || !self.afi_negotiated(candidate.route.afi())
+        {
+            return None;
+        }
+
+        let mut route = candidate.route.clone();
+        if ebgp {
+            route.path_attributes.retain(|a| {
+                !matches!(
+                    a,
+                    crate::packet::attribute::PathAttribute::LocalPref(_)
+                        | crate::packet::attribute::PathAttribute::MultiExitDisc(_)
+                )
+            });
+            if route.as_path().is_none() {
+                route
+                    .path_attributes
+                    .push(crate::packet::attribute::PathAttribute::AsPath(
+                        crate::packet::attribute::AsPath::default(),
+                    ));
+            }
+            for attribute in &mut route.path_attributes {
+                if let crate::packet::attribute::PathAttribute::AsPath(as_path) = attribute {
+                    as_path.prepend(self.config.local_as);
+                }
+            }
+        } else if route.local_pref().is_none() {
+            route
+                .path_attributes
+                .push(crate::packet::attribute::PathAttribute::LocalPref(
+                    crate::decision::DEFAULT_LOCAL_PREF,
+                ));
+        }
+
+        if ebgp || route.next_hop.is_unspecified() {
+            let Some(next_hop) = self.next_hop_self(route.afi()) else {
+                tracing::warn!("no next hop to advertise {} with", route.prefix);
+                return None;
+            };
+            route.next_hop = next_hop;
+        }
+        Some(route)
+    }
+
+    // IPv6 routes over an IPv4 session carry the IPv4-mapped local address
+    fn next_hop_self(&self, afi: crate::types::Afi) -> Option<crate::packet::attribute::NextHop> {
+        match (afi, self.config.local_ip) {
+            (crate::types::Afi::Ipv4, std::net::IpAddr::V4(addr)) => {
+                Some(crate::packet::attribute::NextHop::V4(addr))
+            }
+            (crate::types::Afi::Ipv6, std::net::IpAddr::V4(addr)) => {
+                Some(crate::packet::attribute::NextHop::V6 {
+                    global: addr.to_ipv6_mapped(),
+                    link_local: None,
+                })
+            }
+            (crate::types::Afi::Ipv6, std::net::IpAddr::V6(addr)) => {
+                Some(crate::packet::attribute::NextHop::V6 {
+                    global: addr,
+                    link_local: None,
+                })
+            }
+            (crate::types::Afi::Ipv4, std::net::IpAddr::V6(_)) => None,
+        }
+    }
+
+    // peers without the multiprotocol capability only support IPv4 unicast (RFC 4760)
+    fn afi_negotiated(&self, afi: crate::types::Afi) -> bool {
+        let mut multiprotocol = self
+            .negotiated_capabilities
+            .iter()
+            .filter_map(|capability| match capability {
+                crate::packet::capability::Capability::Multiprotocol(afi, safi) => {
+                    Some((*afi, *safi))
                 }
+                _ => None,
+            })
+            .peekable();
+        if multiprotocol.peek().is_none() {
+            return afi == crate::types::Afi::Ipv4;
+        }
+        multiprotocol.any(|negotiated| negotiated == (afi, crate::types::Safi::Unicast))
+    }
+
+    // the four-octet AS capability is always advertised since it carries the local AS
+    fn local_capabilities(&self) -> Vec<crate::packet::capability::Capability> {
+        let mut capabilities = self.config.capabilities.clone();
+        capabilities.retain(|capability| {
+            !matches!(
+                capability,
+                crate::packet::capability::Capability::FourOctetAs(_)
+            )
+        });
+        capabilities.push(crate::packet::capability::Capability::FourOctetAs(
+            self.config.local_as,
+        ));
+        capabilities
+    }
+
+    fn open_connection(&mut self) {
+        // a shared listener dispatches inbound connections to passive peers
+        if self.config.mode == crate::config::Mode::Passive && self.inbound.is_some() {
+            return;
+        }
+        self.connect_task = Some(match self.config.mode {
+            crate::config::Mode::Active => {
+                let connect = self.connector.connect(&self.config);
+                tokio::spawn(
+                    async move { Ok(crate::connection::Connection::outgoing(connect.await?)) },
+                )
+            }
+            crate::config::Mode::Passive => {
+                let accept = self.connector.accept(&self.config);
+                tokio::spawn(
+                    async move { Ok(crate::connection::Connection::incoming(accept.await?)) },
+                )
+            }
+        });
+    }
+
+    fn close_connection(&mut self) {
+        if let Some(connect_task) = self.connect_task.take() {
+            connect_task.abort();
+        }
+        self.connection = None;
+    }
+
+    async fn accept_connection(&mut self) {
+        self.close_connection();
+        self.connection = self.inbound_connection.take();
+        self.send_open().await;
+    }
+
+    fn reject_connection(&mut self) {
+        if self.inbound_connection.take().is_some() {
+            tracing::warn!(
+                "rejected connection from {} in state {:?}",
+                self.config.remote_ip,
+                self.state
+            );
+        }
+    }
+
+    // the inbound connection is kept until an OPEN tells which connection survives
+    async fn open_collision(&mut self) {
+        if self.collision.is_some() {
+            self.reject_connection();
+            return;
+        }
+        let Some(mut connection) = self.inbound_connection.take() else {
+            return;
+        };
+        if let Err(e) = connection.send(self.open_message()).await {
+            tracing::warn!("failed to send message: {:?}", e);
+            return;
+        }
+        self.collision = Some(connection);
+    }
+
+    // keeps the connection initiated by the speaker with the higher BGP identifier
+    // (RFC 4271 section 6.8) and returns whether the current connection survived
+    async fn resolve_collision(&mut self, remote_id: std::net::Ipv4Addr) -> bool {
+        let Some(collision) = self.collision.take() else {
+            return true;
+        };
+        let outgoing = self
+            .connection
+            .as_ref()
+            .is_some_and(|connection| connection.is_outgoing());
+        if outgoing && self.config.router_id < remote_id {
+            tracing::info!("connection collision resolved in favor of the inbound connection");
+            if let Some(connection) = self.connection.replace(collision) {
+                Self::close_collided(connection).await;
+            }
+            false
+        } else {
+            tracing::info!("connection collision resolved in favor of the existing connection");
+            Self::close_collided(collision).await;
+            true
+        }
+    }
+
+    async fn close_collided(mut connection: crate::connection::Connection<C::Transport>) {
+        let notification = crate::packet::message::Message::new_notification(
+            crate::packet::notification::ErrorCode::Cease(
+                crate::packet::notification::CeaseSubcode::ConnectionCollisionResolution,
+            ),
+            &[],
+        );
+        if let Err(e) = connection.send(notification).await {
+            tracing::warn!("failed to send message: {:?}", e);
+        }
+    }
+
+    fn open_message(&self) -> crate::packet::message::Message {
+        crate::packet::message::Message::new_open(
+            self.config.local_as,
+            self.config.router_id,
+            self.config.hold_time,
+            self.local_capabilities(),
+        )
+    }
+
+    async fn send_open(&mut self) {
+        self.connect_retry_timer.stop();
+        self.send(self.open_message()).await;
+        self.hold_timer.start(crate::constants::LARGE_HOLD_TIME);
+        self.state = crate::state::State::OpenSent;
+    }
+
+    async fn send(&mut self, msg: crate::packet::message::Message) {
+        if let Some(connection) = self.connection.as_mut() {
+            tracing::info!("sending message: {:?}", msg);
+            if let Err(e) = connection.send(msg).await {
+                tracing::warn!("failed to send message: {:?}", e);
+                self.queue.enqueue(crate::event::Event::TcpConnectionFails);
             }
         }
     }
+
+    fn start_connect_retry_timer(&mut self) {
+        self.connect_retry_timer
+            .start(self.connect_retry_interval());
+    }
+
+    // backs off exponentially with the number of consecutive failures and
+    // applies the jitter recommended by RFC 4271 section 10
+    fn connect_retry_interval(&self) -> std::time::Duration {
+        let backoff = self
+            .config
+            .connect_retry_time
+            .saturating_mul(1 << self.connect_retry_counter.min(16));
+        std::cmp::min(backoff, crate::constants::MAX_CONNECT_RETRY_TIME)
+            .mul_f64(rand::random_range(0.75..=1.0))
+    }
+
+    fn start_hold_timer(&mut self) {
+        if self.hold_time.is_zero() {
+            self.hold_timer.stop();
+        } else {
+            self.hold_timer.start(self.hold_time.into());
+        }
+    }
+
+    fn start_keepalive_timer(&mut self) {
+        if self.hold_time.is_zero() {
+            self.keepalive_timer.stop();
+        } else {
+            self.keepalive_timer
+                .start(self.hold_time.keepalive_interval());
+        }
+    }
+
+    fn release_resources(&mut self) {
+        self.close_connection();
+        self.inbound_connection = None;
+        self.collision = None;
+        self.connect_retry_timer.stop();
+        self.idle_hold_timer.stop();
+        self.hold_timer.stop();
+        self.keepalive_timer.stop();
+        self.negotiated_capabilities.clear();
+        if !self.adj_rib_in.clear().is_empty() {
+            self.loc_rib
+                .lock()
+                .unwrap()
+                .remove_peer(self.config.remote_ip);
+        }
+        self.adj_rib_out.clear();
+    }
+
+    fn stop_session(&mut self) {
+        self.release_resources();
+        self.connect_retry_counter = 0;
+        self.state = crate::state::State::Idle;
+    }
+
+    fn drop_to_idle(&mut self) {
+        self.release_resources();
+        self.connect_retry_counter += 1;
+        self.state = crate::state::State::Idle;
+
+        let interval = self.connect_retry_interval();
+        tracing::info!("restarting session in {:?}", interval);
+        self.idle_hold_timer.start(interval);
+    }
+
+    async fn drop_to_idle_with(
+        &mut self,
+        notification: crate::packet::notification::NotificationMessage,
+    ) {
+        self.send(crate::packet::message::Message::Notification(notification))
+            .await;
+        self.drop_to_idle();
+    }
+
+    async fn cease(&mut self) {
+        self.send(crate::packet::message::Message::new_notification(
+            crate::packet::notification::ErrorCode::Cease(
+                crate::packet::notification::CeaseSubcode::AdministrativeShutdown,
+            ),
+            &[],
+        ))
+        .await;
+        self.stop_session();
+    }
+
+    async fn hold_timer_expired(&mut self) {
+        self.drop_to_idle_with(crate::packet::notification::NotificationMessage::new(
+            crate::packet::notification::ErrorCode::HoldTimerExpired,
+            &[],
+        ))
+        .await;
+    }
+
+    async fn fsm_error(&mut self) {
+        self.drop_to_idle_with(crate::packet::notification::NotificationMessage::new(
+            crate::packet::notification::ErrorCode::FiniteStateMachine,
+            &[],
+        ))
+        .await;
+    }
 }
 
 #[cfg(test)]
@@ -86,79 +881,844 @@ mod tests {
     use super::*;
     use std::str::FromStr as _;
 
+    // the passive peer is started first so that it is listening when the active one connects
+    async fn started_memory_peers() -> (
+        Peer<crate::transport::MemoryConnector>,
+        Peer<crate::transport::MemoryConnector>,
+    ) {
+        started_memory_peers_with(
+            "64512 127.0.0.1 64513 127.0.0.2 active",
+            "64513 127.0.0.2 64512 127.0.0.1 passive",
+        )
+        .await
+    }
+
+    async fn started_memory_peers_with(
+        config: &str,
+        remote_config: &str,
+    ) -> (
+        Peer<crate::transport::MemoryConnector>,
+        Peer<crate::transport::MemoryConnector>,
+    ) {
+        let connector = crate::transport::MemoryConnector::new();
+        let remote_config = crate::config::Config::from_str(remote_config).unwrap();
+        let mut remote_peer = Peer::with_connector(
+            remote_config,
+            crate::rib::LocRib::shared(),
+            connector.clone(),
+        );
+        remote_peer.start();
+        remote_peer.next().await;
+
+        let config = crate::config::Config::from_str(config).unwrap();
+        let mut peer = Peer::with_connector(config, crate::rib::LocRib::shared(), connector);
+        peer.start();
+        peer.next().await;
+        (peer, remote_peer)
+    }
+
     #[tokio::test]
     async fn connect_transition() {
-        let mut peer = Peer::new(crate::config::Config::default());
-        peer.start();
+        let (peer, remote_peer) = started_memory_peers().await;
 
-        tokio::spawn(async move {
-            let remote_config =
-                crate::config::Config::from_str("64513 127.0.0.2 64512 127.0.0.1 passive").unwrap();
+        assert_eq!(peer.state, crate::state::State::Connect);
+        assert_eq!(remote_peer.state, crate::state::State::Active);
+    }
 
-            let mut remote_peer = Peer::new(remote_config);
-            remote_peer.start();
+    #[tokio::test]
+    async fn open_sent_transition() {
+        let (mut peer, mut remote_peer) = started_memory_peers().await;
+        peer.next().await;
+        remote_peer.next().await;
+
+        assert_eq!(peer.state, crate::state::State::OpenSent);
+        assert_eq!(remote_peer.state, crate::state::State::OpenSent);
+    }
+
+    #[tokio::test]
+    async fn open_confirm_transition() {
+        let (mut peer, mut remote_peer) = started_memory_peers().await;
+        for _ in 0..2 {
+            peer.next().await;
             remote_peer.next().await;
-        });
+        }
 
-        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+        assert_eq!(peer.state, crate::state::State::OpenConfirm);
+        assert_eq!(remote_peer.state, crate::state::State::OpenConfirm);
+    }
+
+    #[tokio::test]
+    async fn established_transition_with_remote() {
+        let (mut peer, mut remote_peer) = started_memory_peers().await;
+        for _ in 0..3 {
+            peer.next().await;
+            remote_peer.next().await;
+        }
+
+        assert_eq!(peer.state, crate::state::State::Established);
+        assert_eq!(remote_peer.state, crate::state::State::Established);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn established_transition_with_ipv6_remote() {
+        let (mut peer, mut remote_peer) = started_memory_peers_with(
+            "64512 ::1 64513 ::1 active 90 120 10.0.0.1",
+            "64513 ::1 64512 ::1 passive 90 120 10.0.0.2",
+        )
+        .await;
+        for _ in 0..3 {
+            peer.next().await;
+            remote_peer.next().await;
+        }
+
+        assert_eq!(peer.state, crate::state::State::Established);
+        assert_eq!(remote_peer.state, crate::state::State::Established);
+    }
+
+    #[tokio::test]
+    async fn connection_fails_transition() {
+        let mut peer = Peer::new(crate::config::Config::default());
+        peer.state = crate::state::State::Connect;
+        peer.queue.enqueue(crate::event::Event::TcpConnectionFails);
         peer.next().await;
-        assert_eq!(peer.state, crate::state::State::Connect);
+
+        assert_eq!(peer.state, crate::state::State::Active);
     }
 
     #[tokio::test]
-    async fn open_sent_transition() {
+    async fn established_transition() {
         let mut peer = Peer::new(crate::config::Config::default());
-        peer.start();
+        peer.state = crate::state::State::OpenConfirm;
+        peer.queue.enqueue(crate::event::Event::KeepAliveMsg);
+        peer.next().await;
 
-        tokio::spawn(async move {
-            let remote_config =
-                crate::config::Config::from_str("64513 127.0.0.2 64512 127.0.0.1 passive").unwrap();
+        assert_eq!(peer.state, crate::state::State::Established);
+    }
 
-            let mut remote_peer = Peer::new(remote_config);
-            remote_peer.start();
-            for _ in 0..2 {
-                remote_peer.next().await;
-            }
-        });
+    #[tokio::test]
+    async fn stop_transition() {
+        let mut peer = Peer::new(crate::config::Config::default());
+        peer.state = crate::state::State::Established;
+        peer.stop();
+        peer.next().await;
 
-        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+        assert_eq!(peer.state, crate::state::State::Idle);
+        assert_eq!(peer.connect_retry_counter, 0);
+    }
 
-        for _ in 0..2 {
-            peer.next().await;
-        }
+    #[tokio::test]
+    async fn unexpected_event_transition() {
+        let mut peer = Peer::new(crate::config::Config::default());
+        peer.state = crate::state::State::OpenConfirm;
+        peer.queue.enqueue(crate::event::Event::UpdateMsg(
+            crate::packet::update::UpdateMessage::new(vec![], vec![], vec![]),
+        ));
+        peer.next().await;
 
-        assert_eq!(peer.state, crate::state::State::OpenSent);
+        assert_eq!(peer.state, crate::state::State::Idle);
+        assert_eq!(peer.connect_retry_counter, 1);
     }
 
     #[tokio::test]
-    async fn open_confirm_transition() {
+    async fn bad_peer_as_transition() {
         let mut peer = Peer::new(crate::config::Config::default());
-        peer.start();
+        peer.state = crate::state::State::OpenSent;
+        peer.queue.enqueue(crate::event::Event::BgpOpen(
+            crate::packet::open::OpenMessage::new(
+                64514.into(),
+                "127.0.0.2".parse().unwrap(),
+                crate::types::HoldTime::new(),
+                vec![],
+            ),
+        ));
+        peer.next().await;
 
-        tokio::spawn(async move {
-            let remote_config =
-                crate::config::Config::from_str("64513 127.0.0.2 64512 127.0.0.1 passive").unwrap();
-            let mut remote_peer = Peer::new(remote_config);
-            remote_peer.start();
+        assert_eq!(peer.state, crate::state::State::Idle);
+        assert_eq!(peer.connect_retry_counter, 1);
+    }
 
-            for _ in 0..99 {
-                remote_peer.next().await;
-                if remote_peer.state == crate::state::State::OpenConfirm {
-                    break;
-                }
-                tokio::time::sleep(tokio::time::Duration::from_secs_f32(0.1)).await;
-            }
-        });
+    #[tokio::test]
+    async fn notification_transition() {
+        let mut peer = Peer::new(crate::config::Config::default());
+        peer.state = crate::state::State::Established;
+        peer.queue.enqueue(crate::event::Event::NotifMsg(
+            crate::packet::notification::NotificationMessage::new(
+                crate::packet::notification::ErrorCode::Cease(
+                    crate::packet::notification::CeaseSubcode::AdministrativeShutdown,
+                ),
+                &[],
+            ),
+        ));
+        peer.next().await;
+
+        assert_eq!(peer.state, crate::state::State::Idle);
+    }
+
+    #[tokio::test]
+    async fn hold_time_negotiation() {
+        let mut peer = Peer::new(crate::config::Config::default());
+        peer.state = crate::state::State::OpenSent;
+        peer.queue.enqueue(crate::event::Event::BgpOpen(
+            crate::packet::open::OpenMessage::new(
+                64513.into(),
+                "127.0.0.2".parse().unwrap(),
+                crate::types::HoldTime::try_from(30).unwrap(),
+                vec![],
+            ),
+        ));
+        peer.next().await;
+
+        assert_eq!(peer.state, crate::state::State::OpenConfirm);
+        assert_eq!(
+            peer.hold_time,
+            crate::types::HoldTime::try_from(30).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn capability_negotiation() {
+        let mut peer = Peer::new(crate::config::Config::default());
+        peer.state = crate::state::State::OpenSent;
+        peer.queue.enqueue(crate::event::Event::BgpOpen(
+            crate::packet::open::OpenMessage::new(
+                64513.into(),
+                "127.0.0.2".parse().unwrap(),
+                crate::types::HoldTime::new(),
+                vec![
+                    crate::packet::capability::Capability::Multiprotocol(
+                        crate::types::Afi::Ipv4,
+                        crate::types::Safi::Unicast,
+                    ),
+                    crate::packet::capability::Capability::Unknown {
+                        code: 2,
+                        value: bytes::BytesMut::new(),
+                    },
+                ],
+            ),
+        ));
+        peer.next().await;
+
+        assert_eq!(peer.state, crate::state::State::OpenConfirm);
+        assert_eq!(
+            peer.negotiated_capabilities,
+            vec![crate::packet::capability::Capability::Multiprotocol(
+                crate::types::Afi::Ipv4,
+                crate::types::Safi::Unicast,
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn four_octet_as_negotiation() {
+        let config = crate::config::Config {
+            remote_as: 4200000000.into(),
+            ..Default::default()
+        };
+        let mut peer = Peer::new(config);
+        peer.state = crate::state::State::OpenSent;
+        peer.queue.enqueue(crate::event::Event::BgpOpen(
+            crate::packet::open::OpenMessage::new(
+                4200000000.into(),
+                "127.0.0.2".parse().unwrap(),
+                crate::types::HoldTime::new(),
+                vec![crate::packet::capability::Capability::FourOctetAs(
+                    4200000000.into(),
+                )],
+            ),
+        ));
+        peer.next().await;
+
+        assert_eq!(peer.state, crate::state::State::OpenConfirm);
+        assert_eq!(
+            peer.negotiated_capabilities,
+            vec![crate::packet::capability::Capability::FourOctetAs(
+                4200000000.into()
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn dual_stack_routes() {
+        let mut peer = Peer::new(crate::config::Config::default());
+        peer.state = crate::state::State::Established;
+        peer.negotiated_capabilities = vec![crate::packet::capability::Capability::Multiprotocol(
+            crate::types::Afi::Ipv6,
+            crate::types::Safi::Unicast,
+        )];
+        let v6_prefix: crate::packet::prefix::Prefix =
+            crate::packet::prefix::Ipv6Prefix::new("2001:db8::".parse().unwrap(), 32)
+                .unwrap()
+                .into();
+        peer.queue.enqueue(crate::event::Event::UpdateMsg(
+            crate::packet::update::UpdateMessage::new(
+                vec![],
+                vec![
+                    crate::packet::attribute::PathAttribute::Origin(
+                        crate::packet::attribute::Origin::Igp,
+                    ),
+                    crate::packet::attribute::PathAttribute::AsPath(
+                        crate::packet::attribute::AsPath::default(),
+                    ),
+                    crate::packet::attribute::PathAttribute::NextHop("10.0.0.2".parse().unwrap()),
+                    crate::packet::attribute::PathAttribute::MpReachNlri(
+                        crate::packet::attribute::MpReachNlri {
+                            afi: crate::types::Afi::Ipv6,
+                            safi: crate::types::Safi::Unicast,
+                            next_hop: crate::packet::attribute::NextHop::V6 {
+                                global: "2001:db8::2".parse().unwrap(),
+                                link_local: None,
+                            },
+                            nlri: vec![v6_prefix],
+                        },
+                    ),
+                ],
+                vec![
+                    crate::packet::prefix::Ipv4Prefix::new("10.0.0.0".parse().unwrap(), 8).unwrap(),
+                ],
+            ),
+        ));
+        peer.next().await;
+
+        // IPv4 unicast was not negotiated as the peer advertised multiprotocol capabilities
+        assert_eq!(peer.state, crate::state::State::Established);
+        assert_eq!(
+            peer.adj_rib_in
+                .table()
+                .routes(crate::types::Afi::Ipv4)
+                .count(),
+            0
+        );
+        assert!(peer.adj_rib_in.table().get(&v6_prefix).is_some());
+        assert!(peer.loc_rib.lock().unwrap().best(&v6_prefix).is_some());
+
+        peer.queue.enqueue(crate::event::Event::UpdateMsg(
+            crate::packet::update::UpdateMessage::new(
+                vec![],
+                vec![crate::packet::attribute::PathAttribute::MpUnreachNlri(
+                    crate::packet::attribute::MpUnreachNlri {
+                        afi: crate::types::Afi::Ipv6,
+                        safi: crate::types::Safi::Unicast,
+                        withdrawn_routes: vec![v6_prefix],
+                    },
+                )],
+                vec![],
+            ),
+        ));
+        peer.next().await;
+
+        assert!(peer.adj_rib_in.table().is_empty());
+        assert!(peer.loc_rib.lock().unwrap().best(&v6_prefix).is_none());
+    }
+
+    #[tokio::test]
+    async fn routes_are_removed_when_session_drops() {
+        let loc_rib = crate::rib::LocRib::shared();
+        let other_peer: std::net::IpAddr = "127.0.0.3".parse().unwrap();
+        let route = |next_hop: &str| crate::route::Route {
+            prefix: crate::packet::prefix::Ipv4Prefix::new("10.0.0.0".parse().unwrap(), 8)
+                .unwrap()
+                .into(),
+            next_hop: crate::packet::attribute::NextHop::V4(next_hop.parse().unwrap()),
+            path_attributes: vec![],
+        };
+        let other_source = crate::rib::Source {
+            address: other_peer,
+            router_id: "10.0.0.3".parse().unwrap(),
+            ebgp: true,
+        };
+        loc_rib
+            .lock()
+            .unwrap()
+            .insert(crate::rib::Candidate::new(other_source, route("10.0.0.3")));
+
+        let mut peer = Peer::with_loc_rib(crate::config::Config::default(), loc_rib.clone());
+        peer.state = crate::state::State::Established;
+        peer.queue.enqueue(crate::event::Event::UpdateMsg(
+            crate::packet::update::UpdateMessage::new(
+                vec![],
+                vec![
+                    crate::packet::attribute::PathAttribute::Origin(
+                        crate::packet::attribute::Origin::Igp,
+                    ),
+                    crate::packet::attribute::PathAttribute::AsPath(
+                        crate::packet::attribute::AsPath::default(),
+                    ),
+                    crate::packet::attribute::PathAttribute::NextHop("10.0.0.2".parse().unwrap()),
+                ],
+                vec![
+                    crate::packet::prefix::Ipv4Prefix::new("10.0.0.0".parse().unwrap(), 8).unwrap(),
+                ],
+            ),
+        ));
+        peer.next().await;
+
+        let prefix = route("10.0.0.2").prefix;
+        assert_eq!(peer.adj_rib_in.table().len(), 1);
+        assert_eq!(loc_rib.lock().unwrap().candidates(&prefix).len(), 2);
+
+        peer.queue.enqueue(crate::event::Event::TcpConnectionFails);
+        peer.next().await;
+
+        assert_eq!(peer.state, crate::state::State::Idle);
+        assert!(peer.adj_rib_in.table().is_empty());
+        assert_eq!(
+            loc_rib.lock().unwrap().best(&prefix),
+            Some(&route("10.0.0.3"))
+        );
+        assert_eq!(loc_rib.lock().unwrap().candidates(&prefix).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn unsupported_capability_transition() {
+        let config = crate::config::Config {
+            required_capabilities: vec![crate::packet::capability::Capability::Multiprotocol(
+                crate::types::Afi::Ipv4,
+                crate::types::Safi::Unicast,
+            )],
+            ..Default::default()
+        };
+        let mut peer = Peer::new(config);
+        peer.state = crate::state::State::OpenSent;
+        peer.queue.enqueue(crate::event::Event::BgpOpen(
+            crate::packet::open::OpenMessage::new(
+                64513.into(),
+                "127.0.0.2".parse().unwrap(),
+                crate::types::HoldTime::new(),
+                vec![],
+            ),
+        ));
+        peer.next().await;
+
+        assert_eq!(peer.state, crate::state::State::Idle);
+        assert!(peer.negotiated_capabilities.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn hold_timer_expires_transition() {
+        let mut peer = Peer::new(crate::config::Config::default());
+        peer.state = crate::state::State::Established;
+        peer.start_hold_timer();
+        let start = tokio::time::Instant::now();
+
+        peer.next().await;
+        assert_eq!(
+            tokio::time::Instant::now() - start,
+            std::time::Duration::from(peer.config.hold_time)
+        );
+        assert_eq!(peer.state, crate::state::State::Idle);
+        assert_eq!(peer.connect_retry_counter, 1);
+    }
+
+    #[test]
+    fn connect_retry_backoff() {
+        let config = crate::config::Config {
+            connect_retry_time: std::time::Duration::from_secs(10),
+            ..Default::default()
+        };
+        let mut peer = Peer::new(config);
+
+        let interval = peer.connect_retry_interval();
+        assert!(interval >= std::time::Duration::from_millis(7500));
+        assert!(interval <= std::time::Duration::from_secs(10));
+
+        peer.connect_retry_counter = 3;
+        let interval = peer.connect_retry_interval();
+        assert!(interval >= std::time::Duration::from_secs(60));
+        assert!(interval <= std::time::Duration::from_secs(80));
+
+        peer.connect_retry_counter = 100;
+        assert!(peer.connect_retry_interval() <= crate::constants::MAX_CONNECT_RETRY_TIME);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn connect_retry_transition() {
+        // nothing listens on the in-memory transport, so every attempt fails
+        let mut peer = memory_peer(crate::config::Config::default());
+        peer.start();
 
-        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
         for _ in 0..99 {
             peer.next().await;
-            if peer.state == crate::state::State::OpenConfirm {
+            if peer.connect_retry_counter > 2 {
                 break;
             }
-            tokio::time::sleep(tokio::time::Duration::from_secs_f32(0.1)).await;
         }
 
+        assert!(peer.connect_retry_counter > 2);
+        assert_ne!(peer.state, crate::state::State::Idle);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn automatic_restart() {
+        let mut peer = memory_peer(crate::config::Config::default());
+        peer.state = crate::state::State::Established;
+        peer.queue.enqueue(crate::event::Event::TcpConnectionFails);
+        peer.next().await;
+        assert_eq!(peer.state, crate::state::State::Idle);
+
+        let restart = peer.idle_hold_timer.remaining().unwrap();
+        let start = tokio::time::Instant::now();
+        peer.next().await;
+        // tokio timers fire on millisecond boundaries
+        let elapsed = tokio::time::Instant::now() - start;
+        assert!(elapsed >= restart && elapsed - restart < std::time::Duration::from_millis(1));
+        assert_ne!(peer.state, crate::state::State::Idle);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn keepalive_timer_transition() {
+        let mut peer = memory_peer(crate::config::Config::default());
+        let (local, remote) = stream_pair();
+        peer.connection = Some(crate::connection::Connection::outgoing(local));
+        peer.state = crate::state::State::Established;
+        peer.start_hold_timer();
+        peer.start_keepalive_timer();
+        let start = tokio::time::Instant::now();
+
+        peer.next().await;
+        let interval = peer.hold_time.keepalive_interval();
+        assert_eq!(tokio::time::Instant::now() - start, interval);
+        assert_eq!(peer.state, crate::state::State::Established);
+        assert_eq!(peer.keepalive_timer.remaining(), Some(interval));
+        assert!(matches!(
+            next_message(remote).await,
+            crate::packet::message::Message::KeepAlive(_)
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stop_from_handle() {
+        let mut peer = Peer::new(crate::config::Config::default());
+        peer.state = crate::state::State::Established;
+
+        let handle = peer.handle();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            handle.stop();
+        });
+        peer.next().await;
+
+        assert_eq!(peer.state, crate::state::State::Idle);
+    }
+
+    #[tokio::test]
+    async fn networks_are_advertised() {
+        let config = crate::config::Config::default();
+        let loc_rib = crate::rib::LocRib::shared();
+        let prefix = "10.100.210.0/24".parse().unwrap();
+        loc_rib.lock().unwrap().insert(crate::rib::Candidate::new(
+            crate::rib::Source::local(config.router_id),
+            crate::route::Route::local(prefix),
+        ));
+        let mut peer = Peer::with_loc_rib(config, loc_rib.clone());
+
+        peer.state = crate::state::State::OpenConfirm;
+        peer.queue.enqueue(crate::event::Event::KeepAliveMsg);
+        peer.next().await;
+
+        assert_eq!(peer.state, crate::state::State::Established);
+        let route = peer.adj_rib_out.table().get(&prefix).unwrap();
+        assert_eq!(
+            route.next_hop,
+            crate::packet::attribute::NextHop::V4("127.0.0.1".parse().unwrap())
+        );
+        assert_eq!(route.origin(), Some(crate::packet::attribute::Origin::Igp));
+        assert_eq!(route.neighbor_as(), Some(64512.into()));
+    }
+
+    #[tokio::test]
+    async fn learned_routes_are_advertised_to_other_peers() {
+        let loc_rib = crate::rib::LocRib::shared();
+        let mut peer = Peer::with_loc_rib(crate::config::Config::default(), loc_rib.clone());
+        peer.state = crate::state::State::Established;
+        let other_config = crate::config::Config {
+            remote_as: 64514.into(),
+            remote_ip: std::net::Ipv4Addr::new(127, 0, 0, 3).into(),
+            ..Default::default()
+        };
+        let mut other_peer = Peer::with_loc_rib(other_config, loc_rib.clone());
+        other_peer.state = crate::state::State::Established;
+
+        peer.queue.enqueue(crate::event::Event::UpdateMsg(
+            crate::packet::update::UpdateMessage::new(
+                vec![],
+                vec![
+                    crate::packet::attribute::PathAttribute::Origin(
+                        crate::packet::attribute::Origin::Igp,
+                    ),
+                    crate::packet::attribute::PathAttribute::AsPath(
+                        crate::packet::attribute::AsPath::new(vec![
+                            crate::packet::attribute::AsPathSegment::AsSequence(vec![64513.into()]),
+                        ]),
+                    ),
+                    crate::packet::attribute::PathAttribute::NextHop("127.0.0.2".parse().unwrap()),
+                    crate::packet::attribute::PathAttribute::MultiExitDisc(10),
+                ],
+                vec![
+                    crate::packet::prefix::Ipv4Prefix::new("10.0.0.0".parse().unwrap(), 8).unwrap(),
+                ],
+            ),
+        ));
+        peer.next().await;
+        peer.queue.enqueue(crate::event::Event::LocRibChanged);
+        peer.next().await;
+        other_peer.queue.enqueue(crate::event::Event::LocRibChanged);
+        other_peer.next().await;
+
+        let prefix = "10.0.0.0/8".parse().unwrap();
+        // routes are not sent back to the peer they were learned from
+        assert!(peer.adj_rib_out.table().is_empty());
+        let route = other_peer.adj_rib_out.table().get(&prefix).unwrap();
+        assert_eq!(
+            route.next_hop,
+            crate::packet::attribute::NextHop::V4("127.0.0.1".parse().unwrap())
+        );
+        assert_eq!(route.as_path().unwrap().path_len(), 2);
+        assert_eq!(route.med(), None);
+
+        peer.queue.enqueue(crate::event::Event::TcpConnectionFails);
+        peer.next().await;
+        other_peer.queue.enqueue(crate::event::Event::LocRibChanged);
+        other_peer.next().await;
+        assert!(other_peer.adj_rib_out.table().is_empty());
+    }
+
+    #[tokio::test]
+    async fn connection_from_listener_transition() {
+        let listener = crate::listener::Listener::bind("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        let config =
+            crate::config::Config::from_str("64512 127.0.0.1 64513 127.0.0.1 passive").unwrap();
+        let mut peer = Peer::new(config);
+        peer.listen(&listener);
+        tokio::spawn({
+            let listener = listener.clone();
+            async move { listener.run().await }
+        });
+        peer.start();
+        peer.next().await;
+        assert_eq!(peer.state, crate::state::State::Active);
+        assert!(peer.connect_task.is_none());
+
+        let _stream = tokio::net::TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        peer.next().await;
+        assert_eq!(peer.state, crate::state::State::OpenSent);
+        assert!(peer.connection.is_some());
+    }
+
+    fn memory_peer(config: crate::config::Config) -> Peer<crate::transport::MemoryConnector> {
+        Peer::with_connector(
+            config,
+            crate::rib::LocRib::shared(),
+            crate::transport::MemoryConnector::new(),
+        )
+    }
+
+    fn stream_pair() -> (tokio::io::DuplexStream, tokio::io::DuplexStream) {
+        tokio::io::duplex(crate::constants::MEMORY_TRANSPORT_BUFFER)
+    }
+
+    async fn next_message(stream: tokio::io::DuplexStream) -> crate::packet::message::Message {
+        use futures::StreamExt as _;
+
+        let mut framed =
+            tokio_util::codec::Framed::new(stream, crate::packet::codec::MessageCodec::new());
+        framed.next().await.unwrap().unwrap()
+    }
+
+    fn is_collision_cease(message: &crate::packet::message::Message) -> bool {
+        matches!(
+            message,
+            crate::packet::message::Message::Notification(notification)
+                if *notification.error_code()
+                    == crate::packet::notification::ErrorCode::Cease(
+                        crate::packet::notification::CeaseSubcode::ConnectionCollisionResolution,
+                    )
+        )
+    }
+
+    #[tokio::test]
+    async fn collision_in_open_confirm_keeps_higher_identifier() {
+        // the remote speaker has the higher identifier, so its connection wins
+        let mut peer = memory_peer(crate::config::Config::default());
+        let (outgoing, outgoing_remote) = stream_pair();
+        let (incoming, incoming_remote) = stream_pair();
+        peer.state = crate::state::State::OpenConfirm;
+        peer.remote_router_id = "127.0.0.2".parse().unwrap();
+        peer.connection = Some(crate::connection::Connection::outgoing(outgoing));
+        peer.inbound_connection = Some(crate::connection::Connection::incoming(incoming));
+        peer.queue
+            .enqueue(crate::event::Event::TcpConnectionConfirmed);
+        peer.next().await;
+
+        assert_eq!(peer.state, crate::state::State::OpenSent);
+        assert!(!peer.connection.as_ref().unwrap().is_outgoing());
+        assert!(is_collision_cease(&next_message(outgoing_remote).await));
+        assert!(matches!(
+            next_message(incoming_remote).await,
+            crate::packet::message::Message::Open(_)
+        ));
+
+        // otherwise the existing connection is kept
+        let mut peer = memory_peer(crate::config::Config::default());
+        let (outgoing, _outgoing_remote) = stream_pair();
+        let (incoming, incoming_remote) = stream_pair();
+        peer.state = crate::state::State::OpenConfirm;
+        peer.remote_router_id = "10.0.0.1".parse().unwrap();
+        peer.connection = Some(crate::connection::Connection::outgoing(outgoing));
+        peer.inbound_connection = Some(crate::connection::Connection::incoming(incoming));
+        peer.queue
+            .enqueue(crate::event::Event::TcpConnectionConfirmed);
+        peer.next().await;
+
         assert_eq!(peer.state, crate::state::State::OpenConfirm);
+        assert!(peer.connection.as_ref().unwrap().is_outgoing());
+        assert!(is_collision_cease(&next_message(incoming_remote).await));
+    }
+
+    #[tokio::test]
+    async fn collision_in_open_sent_is_resolved_by_open() {
+        use futures::StreamExt as _;
+
+        let mut peer = memory_peer(crate::config::Config::default());
+        let (outgoing, _outgoing_remote) = stream_pair();
+        let (incoming, incoming_remote) = stream_pair();
+        peer.state = crate::state::State::OpenSent;
+        peer.connection = Some(crate::connection::Connection::outgoing(outgoing));
+        peer.inbound_connection = Some(crate::connection::Connection::incoming(incoming));
+        peer.queue
+            .enqueue(crate::event::Event::TcpConnectionConfirmed);
+        peer.next().await;
+
+        assert_eq!(peer.state, crate::state::State::OpenSent);
+        assert!(peer.collision.is_some());
+
+        peer.queue.enqueue(crate::event::Event::BgpOpen(
+            crate::packet::open::OpenMessage::new(
+                64513.into(),
+                "10.0.0.1".parse().unwrap(),
+                crate::types::HoldTime::new(),
+                vec![],
+            ),
+        ));
+        peer.next().await;
+
+        assert_eq!(peer.state, crate::state::State::OpenConfirm);
+        assert!(peer.collision.is_none());
+        let mut framed = tokio_util::codec::Framed::new(
+            incoming_remote,
+            crate::packet::codec::MessageCodec::new(),
+        );
+        assert!(matches!(
+            framed.next().await.unwrap().unwrap(),
+            crate::packet::message::Message::Open(_)
+        ));
+        assert!(is_collision_cease(&framed.next().await.unwrap().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn malformed_notification_is_not_answered() {
+        use tokio::io::AsyncReadExt as _;
+
+        let mut peer = memory_peer(crate::config::Config::default());
+        let (local, mut remote) = stream_pair();
+        peer.connection = Some(crate::connection::Connection::outgoing(local));
+        peer.state = crate::state::State::Established;
+        peer.queue.enqueue(crate::event::Event::NotifMsgErr);
+        peer.next().await;
+
+        assert_eq!(peer.state, crate::state::State::Idle);
+        // the connection is closed without a NOTIFICATION being sent
+        assert_eq!(remote.read(&mut [0; 1]).await.unwrap(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn start_is_ignored_while_established() {
+        let (mut peer, mut remote_peer) = started_memory_peers().await;
+        for _ in 0..3 {
+            peer.next().await;
+            remote_peer.next().await;
+        }
+        assert_eq!(peer.state, crate::state::State::Established);
+
+        peer.handle().start();
+        peer.next().await;
+        peer.queue
+            .enqueue(crate::event::Event::IdleHoldTimerExpires);
+        peer.next().await;
+        assert_eq!(peer.state, crate::state::State::Established);
+
+        // a NOTIFICATION would have taken the remote peer down
+        let timeout = std::time::Duration::from_secs(1);
+        assert!(tokio::time::timeout(timeout, remote_peer.next())
+            .await
+            .is_err());
+        assert_eq!(remote_peer.state, crate::state::State::Established);
+    }
+
+    fn update_with(
+        as_path: &[u32],
+        mut path_attributes: Vec<crate::packet::attribute::PathAttribute>,
+    ) -> crate::packet::update::UpdateMessage {
+        path_attributes.extend([
+            crate::packet::attribute::PathAttribute::Origin(crate::packet::attribute::Origin::Igp),
+            crate::packet::attribute::PathAttribute::AsPath(crate::packet::attribute::AsPath::new(
+                vec![crate::packet::attribute::AsPathSegment::AsSequence(
+                    as_path.iter().map(|asn| (*asn).into()).collect(),
+                )],
+            )),
+            crate::packet::attribute::PathAttribute::NextHop("127.0.0.2".parse().unwrap()),
+        ]);
+        crate::packet::update::UpdateMessage::new(
+            vec![],
+            path_attributes,
+            vec![crate::packet::prefix::Ipv4Prefix::new("10.0.0.0".parse().unwrap(), 8).unwrap()],
+        )
+    }
+
+    #[tokio::test]
+    async fn local_pref_from_ebgp_is_ignored() {
+        let loc_rib = crate::rib::LocRib::shared();
+        let mut ebgp_peer = Peer::with_loc_rib(crate::config::Config::default(), loc_rib.clone());
+        let ibgp_config = crate::config::Config {
+            remote_as: 64512.into(),
+            remote_ip: std::net::Ipv4Addr::new(127, 0, 0, 3).into(),
+            ..Default::default()
+        };
+        let mut ibgp_peer = Peer::with_loc_rib(ibgp_config, loc_rib.clone());
+
+        ebgp_peer.process_update(&update_with(
+            &[64513],
+            vec![crate::packet::attribute::PathAttribute::LocalPref(500)],
+        ));
+        ibgp_peer.process_update(&update_with(
+            &[64513],
+            vec![crate::packet::attribute::PathAttribute::LocalPref(200)],
+        ));
+
+        let prefix = "10.0.0.0/8".parse().unwrap();
+        let loc_rib = loc_rib.lock().unwrap();
+        // the first candidate is the best one
+        let best = &loc_rib.candidates(&prefix)[0];
+        assert_eq!(best.source.address, std::net::IpAddr::from([127, 0, 0, 3]));
+        assert!(loc_rib
+            .candidates(&prefix)
+            .iter()
+            .all(|candidate| !candidate.source.ebgp || candidate.route.local_pref().is_none()));
+    }
+
+    #[tokio::test]
+    async fn looped_route_withdraws_previous_one() {
+        let loc_rib = crate::rib::LocRib::shared();
+        let mut peer = Peer::with_loc_rib(crate::config::Config::default(), loc_rib.clone());
+
+        peer.process_update(&update_with(&[64513], vec![]));
+        assert_eq!(loc_rib.lock().unwrap().best_routes().count(), 1);
+
+        peer.process_update(&update_with(&[64513, 64512], vec![]));
+        assert_eq!(loc_rib.lock().unwrap().best_routes().count(), 0);
+        assert!(peer.adj_rib_in.table().is_empty());
     }
 }
@@ -0,0 +1,394 @@
+pub type SharedLocRib = std::sync::Arc<std::sync::Mutex<LocRib>>;
+
+// routes are kept per address family and indexed by prefix
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RouteTable(
+    std::collections::HashMap<
+        crate::types::Afi,
+        std::collections::HashMap<crate::packet::prefix::Prefix, crate::route::Route>,
+    >,
+);
+
+impl RouteTable {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    // returns the route that was replaced, if any
+    pub fn insert(&mut self, route: crate::route::Route) -> Option<crate::route::Route> {
+        self.0
+            .entry(route.afi())
+            .or_default()
+            .insert(route.prefix, route)
+    }
+
+    pub fn withdraw(
+        &mut self,
+        prefix: &crate::packet::prefix::Prefix,
+    ) -> Option<crate::route::Route> {
+        self.0.get_mut(&prefix.afi())?.remove(prefix)
+    }
+
+    pub fn get(&self, prefix: &crate::packet::prefix::Prefix) -> Option<&crate::route::Route> {
+        self.0.get(&prefix.afi())?.get(prefix)
+    }
+
+    pub fn routes(&self, afi: crate::types::Afi) -> impl Iterator<Item = &crate::route::Route> {
+        self.0
+            .get(&afi)
+            .into_iter()
+            .flat_map(|routes| routes.values())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &crate::route::Route> {
+        self.0.values().flat_map(|routes| routes.values())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.values().map(|routes| routes.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // removes every route and returns their prefixes
+    pub fn clear(&mut self) -> Vec<crate::packet::prefix::Prefix> {
+        self.0
+            .drain()
+            .flat_map(|(_, routes)| routes.into_keys())
+            .collect()
+    }
+}
+
+// routes received from a single peer before any selection
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AdjRibIn(RouteTable);
+
+impl AdjRibIn {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn table(&self) -> &RouteTable {
+        &self.0
+    }
+
+    pub fn insert(&mut self, route: crate::route::Route) -> Option<crate::route::Route> {
+        self.0.insert(route)
+    }
+
+    pub fn withdraw(
+        &mut self,
+        prefix: &crate::packet::prefix::Prefix,
+    ) -> Option<crate::route::Route> {
+        self.0.withdraw(prefix)
+    }
+
+    pub fn clear(&mut self) -> Vec<crate::packet::prefix::Prefix> {
+        self.0.clear()
+    }
+}
+
+// routes selected to be advertised to a single peer
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AdjRibOut(RouteTable);
+
+impl AdjRibOut {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn table(&self) -> &RouteTable {
+        &self.0
+    }
+
+    pub fn insert(&mut self, route: crate::route::Route) -> Option<crate::route::Route> {
+        self.0.insert(route)
+    }
+
+    pub fn withdraw(
+        &mut self,
+        prefix: &crate::packet::prefix::Prefix,
+    ) -> Option<crate::route::Route> {
+        self.0.withdraw(prefix)
+    }
+
+    pub fn clear(&mut self) -> Vec<crate::packet::prefix::Prefix> {
+        self.0.clear()
+    }
+}
+
+// the peer a candidate route was learned from
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct Source {
+    pub address: std::net::IpAddr,
+    pub router_id: std::net::Ipv4Addr,
+    pub ebgp: bool,
+}
+
+impl Source {
+    // routes originated by this speaker are keyed by the unspecified address
+    pub fn local(router_id: std::net::Ipv4Addr) -> Self {
+        Self {
+            address: std::net::Ipv4Addr::UNSPECIFIED.into(),
+            router_id,
+            ebgp: false,
+        }
+    }
+
+    pub fn is_local(&self) -> bool {
+        self.address.is_unspecified()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub source: Source,
+    pub route: crate::route::Route,
+    pub received: tokio::time::Instant,
+}
+
+impl Candidate {
+    pub fn new(source: Source, route: crate::route::Route) -> Self {
+        Self {
+            source,
+            route,
+            received: tokio::time::Instant::now(),
+        }
+    }
+}
+
+// candidate routes from every peer, the first candidate of a prefix being the
+// one chosen by the decision process
+#[derive(Debug)]
+pub struct LocRib {
+    candidates: std::collections::HashMap<crate::packet::prefix::Prefix, Vec<Candidate>>,
+    options: crate::decision::Options,
+    changes: tokio::sync::watch::Sender<()>,
+}
+
+impl Default for LocRib {
+    fn default() -> Self {
+        Self {
+            candidates: Default::default(),
+            options: Default::default(),
+            changes: tokio::sync::watch::Sender::new(()),
+        }
+    }
+}
+
+impl LocRib {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_options(options: crate::decision::Options) -> Self {
+        Self {
+            options,
+            ..Default::default()
+        }
+    }
+
+    pub fn shared() -> SharedLocRib {
+        std::sync::Arc::new(std::sync::Mutex::new(Self::new()))
+    }
+
+    // replaces the candidate previously received from the same peer
+    pub fn insert(&mut self, candidate: Candidate) {
+        let prefix = candidate.route.prefix;
+        let candidates = self.candidates.entry(prefix).or_default();
+        match candidates
+            .iter_mut()
+            .find(|c| c.source.address == candidate.source.address)
+        {
+            Some(c) => *c = candidate,
+            None => candidates.push(candidate),
+        }
+        self.select(&prefix);
+        self.changes.send_replace(());
+    }
+
+    pub fn withdraw(&mut self, peer: std::net::IpAddr, prefix: &crate::packet::prefix::Prefix) {
+        if let Some(candidates) = self.candidates.get_mut(prefix) {
+            candidates.retain(|c| c.source.address != peer);
+            if candidates.is_empty() {
+                self.candidates.remove(prefix);
+            }
+            self.select(prefix);
+            self.changes.send_replace(());
+        }
+    }
+
+    pub fn remove_peer(&mut self, peer: std::net::IpAddr) {
+        let prefixes: Vec<_> = self
+            .candidates
+            .iter()
+            .filter(|(_, candidates)| candidates.iter().any(|c| c.source.address == peer))
+            .map(|(prefix, _)| *prefix)
+            .collect();
+        for prefix in prefixes {
+            self.withdraw(peer, &prefix);
+        }
+    }
+
+    pub fn best(&self, prefix: &crate::packet::prefix::Prefix) -> Option<&crate::route::Route> {
+        self.candidates.get(prefix)?.first().map(|c| &c.route)
+    }
+
+    pub fn candidates(&self, prefix: &crate::packet::prefix::Prefix) -> &[Candidate] {
+        self.candidates
+            .get(prefix)
+            .map_or(&[], |candidates| candidates)
+    }
+
+    pub fn best_routes(&self) -> impl Iterator<Item = &crate::route::Route> {
+        self.best_candidates().map(|c| &c.route)
+    }
+
+    pub fn best_candidates(&self) -> impl Iterator<Item = &Candidate> {
+        self.candidates
+            .values()
+            .filter_map(|candidates| candidates.first())
+    }
+
+    // the receiver is marked changed whenever a candidate is inserted or withdrawn
+    pub fn subscribe(&self) -> tokio::sync::watch::Receiver<()> {
+        self.changes.subscribe()
+    }
+
+    pub fn explain(
+        &self,
+        prefix: &crate::packet::prefix::Prefix,
+    ) -> Option<crate::decision::Explanation> {
+        crate::decision::select(self.candidates(prefix), &self.options)
+            .map(|(_, explanation)| explanation)
+    }
+
+    fn select(&mut self, prefix: &crate::packet::prefix::Prefix) {
+        let Some(candidates) = self.candidates.get_mut(prefix) else {
+            tracing::debug!("{prefix}: no path");
+            return;
+        };
+        if let Some((best, explanation)) = crate::decision::select(candidates, &self.options) {
+            tracing::debug!("{prefix}: {explanation}");
+            candidates.swap(0, best);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(prefix: &str, next_hop: &str) -> crate::route::Route {
+        let (addr, len) = prefix.split_once('/').unwrap();
+        crate::route::Route {
+            prefix: crate::packet::prefix::Ipv4Prefix::new(
+                addr.parse().unwrap(),
+                len.parse().unwrap(),
+            )
+            .unwrap()
+            .into(),
+            next_hop: crate::packet::attribute::NextHop::V4(next_hop.parse().unwrap()),
+            path_attributes: vec![],
+        }
+    }
+
+    #[test]
+    fn route_table_replaces_and_withdraws() {
+        let mut table = RouteTable::new();
+        assert!(table.insert(route("10.0.0.0/8", "192.0.2.1")).is_none());
+        assert_eq!(
+            table.insert(route("10.0.0.0/8", "192.0.2.2")),
+            Some(route("10.0.0.0/8", "192.0.2.1"))
+        );
+        table.insert(route("10.1.0.0/16", "192.0.2.1"));
+        assert_eq!(table.len(), 2);
+
+        let prefix = route("10.0.0.0/8", "192.0.2.2").prefix;
+        assert_eq!(table.get(&prefix), Some(&route("10.0.0.0/8", "192.0.2.2")));
+        assert_eq!(
+            table.withdraw(&prefix),
+            Some(route("10.0.0.0/8", "192.0.2.2"))
+        );
+        assert!(table.withdraw(&prefix).is_none());
+        assert_eq!(table.routes(crate::types::Afi::Ipv4).count(), 1);
+        assert_eq!(table.routes(crate::types::Afi::Ipv6).count(), 0);
+
+        assert_eq!(table.clear().len(), 1);
+        assert!(table.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn loc_rib_keeps_candidates_per_peer() {
+        let peer1: std::net::IpAddr = "127.0.0.2".parse().unwrap();
+        let peer2: std::net::IpAddr = "127.0.0.3".parse().unwrap();
+        let source = |address| Source {
+            address,
+            router_id: "10.0.0.1".parse().unwrap(),
+            ebgp: true,
+        };
+        let mut loc_rib = LocRib::new();
+        loc_rib.insert(Candidate::new(
+            source(peer1),
+            route("10.0.0.0/8", "192.0.2.1"),
+        ));
+        loc_rib.insert(Candidate::new(
+            source(peer2),
+            route("10.0.0.0/8", "192.0.2.2"),
+        ));
+
+        let prefix = route("10.0.0.0/8", "192.0.2.1").prefix;
+        assert_eq!(loc_rib.candidates(&prefix).len(), 2);
+        assert_eq!(
+            loc_rib.best(&prefix),
+            Some(&route("10.0.0.0/8", "192.0.2.1"))
+        );
+
+        // the replaced route becomes the newest one
+        tokio::time::advance(std::time::Duration::from_secs(1)).await;
+        loc_rib.insert(Candidate::new(
+            source(peer1),
+            route("10.0.0.0/8", "192.0.2.3"),
+        ));
+        assert_eq!(loc_rib.candidates(&prefix).len(), 2);
+        assert_eq!(
+            loc_rib.best(&prefix),
+            Some(&route("10.0.0.0/8", "192.0.2.2"))
+        );
+        assert_eq!(
+            loc_rib.explain(&prefix).unwrap().losers,
+            vec![(peer1, crate::decision::Reason::Age)]
+        );
+
+        loc_rib.withdraw(peer2, &prefix);
+        assert_eq!(
+            loc_rib.best(&prefix),
+            Some(&route("10.0.0.0/8", "192.0.2.3"))
+        );
+
+        loc_rib.insert(Candidate::new(
+            source(peer2),
+            route("10.1.0.0/16", "192.0.2.1"),
+        ));
+        loc_rib.remove_peer(peer1);
+        assert!(loc_rib.best(&prefix).is_none());
+        assert_eq!(loc_rib.best_routes().count(), 1);
+    }
+
+    #[test]
+    fn loc_rib_notifies_changes() {
+        let mut loc_rib = LocRib::new();
+        let changes = loc_rib.subscribe();
+        assert!(!changes.has_changed().unwrap());
+
+        let local = Source::local("10.0.0.1".parse().unwrap());
+        assert!(local.is_local());
+        loc_rib.insert(Candidate::new(
+            local,
+            crate::route::Route::local("10.0.0.0/8".parse().unwrap()),
+        ));
+        assert!(changes.has_changed().unwrap());
+    }
+}
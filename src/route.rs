@@ -0,0 +1,265 @@
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Route {
+    pub prefix: crate::packet::prefix::Prefix,
+    pub next_hop: crate::packet::attribute::NextHop,
+    // attributes shared by every route of the UPDATE, without the next hop and MP_* attributes
+    pub path_attributes: Vec<crate::packet::attribute::PathAttribute>,
+}
+
+impl Route {
+    // a route originated by this speaker, whose next hop is filled in when advertised
+    pub fn local(prefix: crate::packet::prefix::Prefix) -> Self {
+        let next_hop = match prefix {
+            crate::packet::prefix::Prefix::V4(_) => {
+                crate::packet::attribute::NextHop::V4(std::net::Ipv4Addr::UNSPECIFIED)
+            }
+            crate::packet::prefix::Prefix::V6(_) => crate::packet::attribute::NextHop::V6 {
+                global: std::net::Ipv6Addr::UNSPECIFIED,
+                link_local: None,
+            },
+        };
+        Self {
+            prefix,
+            next_hop,
+            path_attributes: vec![
+                crate::packet::attribute::PathAttribute::Origin(
+                    crate::packet::attribute::Origin::Igp,
+                ),
+                crate::packet::attribute::PathAttribute::AsPath(
+                    crate::packet::attribute::AsPath::default(),
+                ),
+            ],
+        }
+    }
+
+    pub fn afi(&self) -> crate::types::Afi {
+        self.prefix.afi()
+    }
+
+    pub fn origin(&self) -> Option<crate::packet::attribute::Origin> {
+        self.path_attributes.iter().find_map(|a| match a {
+            crate::packet::attribute::PathAttribute::Origin(origin) => Some(*origin),
+            _ => None,
+        })
+    }
+
+    pub fn as_path(&self) -> Option<&crate::packet::attribute::AsPath> {
+        self.path_attributes.iter().find_map(|a| match a {
+            crate::packet::attribute::PathAttribute::AsPath(as_path) => Some(as_path),
+            _ => None,
+        })
+    }
+
+    pub fn med(&self) -> Option<u32> {
+        self.path_attributes.iter().find_map(|a| match a {
+            crate::packet::attribute::PathAttribute::MultiExitDisc(med) => Some(*med),
+            _ => None,
+        })
+    }
+
+    pub fn local_pref(&self) -> Option<u32> {
+        self.path_attributes.iter().find_map(|a| match a {
+            crate::packet::attribute::PathAttribute::LocalPref(local_pref) => Some(*local_pref),
+            _ => None,
+        })
+    }
+
+    // the AS the route was received from, which is the first AS of the path
+    pub fn neighbor_as(&self) -> Option<crate::types::ASNum> {
+        match self.as_path()?.segments().first()? {
+            crate::packet::attribute::AsPathSegment::AsSequence(asns) => asns.first().copied(),
+            crate::packet::attribute::AsPathSegment::AsSet(_) => None,
+        }
+    }
+
+    // collects routes announced either in the IPv4 NLRI field or in MP_REACH_NLRI
+    pub fn announced(update: &crate::packet::update::UpdateMessage) -> Vec<Self> {
+        let path_attributes: Vec<_> = update
+            .path_attributes()
+            .iter()
+            .filter(|a| {
+                !matches!(
+                    a,
+                    crate::packet::attribute::PathAttribute::NextHop(_)
+                        | crate::packet::attribute::PathAttribute::MpReachNlri(_)
+                        | crate::packet::attribute::PathAttribute::MpUnreachNlri(_)
+                )
+            })
+            .cloned()
+            .collect();
+
+        let mut routes = Vec::new();
+        for attribute in update.path_attributes() {
+            match attribute {
+                crate::packet::attribute::PathAttribute::NextHop(next_hop) => {
+                    routes.extend(update.nlri().iter().map(|prefix| Self {
+                        prefix: (*prefix).into(),
+                        next_hop: crate::packet::attribute::NextHop::V4(*next_hop),
+                        path_attributes: path_attributes.clone(),
+                    }))
+                }
+                crate::packet::attribute::PathAttribute::MpReachNlri(mp_reach) => {
+                    routes.extend(mp_reach.nlri.iter().map(|prefix| Self {
+                        prefix: *prefix,
+                        next_hop: mp_reach.next_hop,
+                        path_attributes: path_attributes.clone(),
+                    }))
+                }
+                _ => {}
+            }
+        }
+        routes
+    }
+
+    // IPv4 routes with an IPv4 next hop use the NLRI field, anything else MP_REACH_NLRI
+    pub fn to_update(&self) -> crate::packet::update::UpdateMessage {
+        let mut path_attributes = self.path_attributes.clone();
+        let mut nlri = vec![];
+        match (self.prefix, self.next_hop) {
+            (
+                crate::packet::prefix::Prefix::V4(prefix),
+                crate::packet::attribute::NextHop::V4(next_hop),
+            ) => {
+                path_attributes.push(crate::packet::attribute::PathAttribute::NextHop(next_hop));
+                nlri.push(prefix);
+            }
+            (prefix, next_hop) => {
+                path_attributes.push(crate::packet::attribute::PathAttribute::MpReachNlri(
+                    crate::packet::attribute::MpReachNlri {
+                        afi: prefix.afi(),
+                        safi: crate::types::Safi::Unicast,
+                        next_hop,
+                        nlri: vec![prefix],
+                    },
+                ))
+            }
+        }
+        path_attributes.sort_by_key(|a| a.type_code());
+        crate::packet::update::UpdateMessage::new(vec![], path_attributes, nlri)
+    }
+
+    pub fn withdrawal(
+        prefix: crate::packet::prefix::Prefix,
+    ) -> crate::packet::update::UpdateMessage {
+        match prefix {
+            crate::packet::prefix::Prefix::V4(prefix) => {
+                crate::packet::update::UpdateMessage::new(vec![prefix], vec![], vec![])
+            }
+            prefix => crate::packet::update::UpdateMessage::new(
+                vec![],
+                vec![crate::packet::attribute::PathAttribute::MpUnreachNlri(
+                    crate::packet::attribute::MpUnreachNlri {
+                        afi: prefix.afi(),
+                        safi: crate::types::Safi::Unicast,
+                        withdrawn_routes: vec![prefix],
+                    },
+                )],
+                vec![],
+            ),
+        }
+    }
+
+    pub fn withdrawn(
+        update: &crate::packet::update::UpdateMessage,
+    ) -> Vec<crate::packet::prefix::Prefix> {
+        let mut prefixes: Vec<crate::packet::prefix::Prefix> = update
+            .withdrawn_routes()
+            .iter()
+            .map(|prefix| (*prefix).into())
+            .collect();
+        for attribute in update.path_attributes() {
+            if let crate::packet::attribute::PathAttribute::MpUnreachNlri(mp_unreach) = attribute {
+                prefixes.extend(mp_unreach.withdrawn_routes.iter().copied());
+            }
+        }
+        prefixes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes_from_update() {
+        let v4_prefix: crate::packet::prefix::Prefix =
+            crate::packet::prefix::Ipv4Prefix::new("10.0.0.0".parse().unwrap(), 8)
+                .unwrap()
+                .into();
+        let v6_prefix: crate::packet::prefix::Prefix =
+            crate::packet::prefix::Ipv6Prefix::new("2001:db8::".parse().unwrap(), 32)
+                .unwrap()
+                .into();
+        let v6_next_hop = crate::packet::attribute::NextHop::V6 {
+            global: "2001:db8::1".parse().unwrap(),
+            link_local: Some("fe80::1".parse().unwrap()),
+        };
+        let origin =
+            crate::packet::attribute::PathAttribute::Origin(crate::packet::attribute::Origin::Igp);
+        let update = crate::packet::update::UpdateMessage::new(
+            vec![crate::packet::prefix::Ipv4Prefix::new("10.1.0.0".parse().unwrap(), 16).unwrap()],
+            vec![
+                origin.clone(),
+                crate::packet::attribute::PathAttribute::NextHop("192.0.2.1".parse().unwrap()),
+                crate::packet::attribute::PathAttribute::MpReachNlri(
+                    crate::packet::attribute::MpReachNlri {
+                        afi: crate::types::Afi::Ipv6,
+                        safi: crate::types::Safi::Unicast,
+                        next_hop: v6_next_hop,
+                        nlri: vec![v6_prefix],
+                    },
+                ),
+            ],
+            vec![crate::packet::prefix::Ipv4Prefix::new("10.0.0.0".parse().unwrap(), 8).unwrap()],
+        );
+
+        assert_eq!(
+            Route::announced(&update),
+            vec![
+                Route {
+                    prefix: v4_prefix,
+                    next_hop: crate::packet::attribute::NextHop::V4("192.0.2.1".parse().unwrap()),
+                    path_attributes: vec![origin.clone()],
+                },
+                Route {
+                    prefix: v6_prefix,
+                    next_hop: v6_next_hop,
+                    path_attributes: vec![origin],
+                },
+            ]
+        );
+        assert_eq!(
+            Route::withdrawn(&update),
+            vec![
+                crate::packet::prefix::Ipv4Prefix::new("10.1.0.0".parse().unwrap(), 16)
+                    .unwrap()
+                    .into()
+            ]
+        );
+    }
+
+    #[test]
+    fn update_from_routes() {
+        let v4_route = Route {
+            next_hop: crate::packet::attribute::NextHop::V4("192.0.2.1".parse().unwrap()),
+            ..Route::local("10.0.0.0/8".parse().unwrap())
+        };
+        let v6_route = Route {
+            next_hop: crate::packet::attribute::NextHop::V6 {
+                global: "2001:db8::1".parse().unwrap(),
+                link_local: None,
+            },
+            ..Route::local("2001:db8::/32".parse().unwrap())
+        };
+        assert!(Route::local(v6_route.prefix).next_hop.is_unspecified());
+
+        for route in [v4_route, v6_route] {
+            let update = route.to_update();
+            assert_eq!(Route::announced(&update), vec![route.clone()]);
+            assert_eq!(
+                Route::withdrawn(&Route::withdrawal(route.prefix)),
+                vec![route.prefix]
+            );
+        }
+    }
+}
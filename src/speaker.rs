@@ -0,0 +1,424 @@
+// what a speaker reports to its subscribers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    // the session with a neighbor moved to another FSM state
+    Session {
+        neighbor: std::net::IpAddr,
+        state: crate::state::State,
+    },
+    // the best route to a prefix changed, `None` meaning that it is unreachable
+    BestRoute {
+        prefix: crate::packet::prefix::Prefix,
+        route: Option<crate::route::Route>,
+    },
+}
+
+#[derive(Debug)]
+struct Neighbor<C: crate::transport::Connector> {
+    handle: crate::peer::PeerHandle,
+    removed: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    task: tokio::task::JoinHandle<()>,
+    local: std::net::SocketAddr,
+    listener: Option<C::Listener>,
+}
+
+// a listener shared by the neighbors on one local address
+#[derive(Debug)]
+struct Bound<C: crate::transport::Connector> {
+    listener: C::Listener,
+    task: tokio::task::JoinHandle<()>,
+}
+
+// a BGP speaker whose neighbors all share one Loc-RIB, meant to be embedded in
+// other applications; clones refer to the same speaker
+#[derive(Debug, Clone)]
+pub struct Speaker<C: crate::transport::Connector = crate::transport::TcpConnector> {
+    router_id: std::net::Ipv4Addr,
+    connector: C,
+    loc_rib: crate::rib::SharedLocRib,
+    neighbors:
+        std::sync::Arc<std::sync::Mutex<std::collections::HashMap<std::net::IpAddr, Neighbor<C>>>>,
+    // also held while adding a neighbor, so that a local address is bound only once
+    listeners: std::sync::Arc<
+        tokio::sync::Mutex<std::collections::HashMap<std::net::SocketAddr, Bound<C>>>,
+    >,
+    events: tokio::sync::broadcast::Sender<Event>,
+}
+
+impl Speaker {
+    pub fn new(router_id: std::net::Ipv4Addr) -> Self {
+        Self::with_connector(router_id, crate::transport::TcpConnector)
+    }
+}
+
+impl<C: crate::transport::Connector> Speaker<C> {
+    // spawns the task reporting best route changes, so it has to be called within a
+    // tokio runtime
+    pub fn with_connector(router_id: std::net::Ipv4Addr, connector: C) -> Self {
+        let (events, _) = tokio::sync::broadcast::channel(crate::constants::SPEAKER_EVENT_CAPACITY);
+        let speaker = Self {
+            router_id,
+            connector,
+            loc_rib: crate::rib::LocRib::shared(),
+            neighbors: Default::default(),
+            listeners: Default::default(),
+            events,
+        };
+        tokio::spawn(Self::report_best_routes(
+            std::sync::Arc::downgrade(&speaker.loc_rib),
+            speaker.events.clone(),
+        ));
+        speaker
+    }
+
+    pub fn router_id(&self) -> std::net::Ipv4Addr {
+        self.router_id
+    }
+
+    pub fn loc_rib(&self) -> crate::rib::SharedLocRib {
+        self.loc_rib.clone()
+    }
+
+    // events that happened before subscribing are not delivered
+    pub fn subscribe(&self) -> impl futures::Stream<Item = Event> {
+        futures::stream::unfold(self.events.subscribe(), |mut events| async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => return Some((event, events)),
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("subscriber missed {skipped} events");
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+
+    // the session is started right away with the router id of the speaker; passive
+    // neighbors share one listener per local address, which active neighbors on that
+    // address use as well to detect connection collisions
+    pub async fn add_neighbor(
+        &self,
+        mut config: crate::config::Config,
+    ) -> Result<(), crate::error::SpeakerErr> {
+        let mut listeners = self.listeners.lock().await;
+        let neighbor = config.remote_ip;
+        if self.neighbors.lock().unwrap().contains_key(&neighbor) {
+            return Err(anyhow::anyhow!("neighbor {neighbor} already exists").into());
+        }
+        config.router_id = self.router_id;
+
+        let local = std::net::SocketAddr::new(config.local_ip, config.listen_port);
+        let listener = match listeners.get(&local) {
+            Some(bound) => Some(bound.listener.clone()),
+            None if config.mode == crate::config::Mode::Passive => {
+                let listener = self
+                    .connector
+                    .bind(local)
+                    .await
+                    .map_err(anyhow::Error::from)?;
+                let task = tokio::spawn({
+                    let listener = listener.clone();
+                    async move { crate::transport::Acceptor::run(&listener).await }
+                });
+                listeners.insert(
+                    local,
+                    Bound {
+                        listener: listener.clone(),
+                        task,
+                    },
+                );
+                Some(listener)
+            }
+            None => None,
+        };
+
+        let mut peer =
+            crate::peer::Peer::with_connector(config, self.loc_rib.clone(), self.connector.clone());
+        if let Some(listener) = &listener {
+            peer.listen(listener);
+        }
+        let handle = peer.handle();
+        let removed = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let events = self.events.clone();
+        peer.start();
+        let task = tokio::spawn({
+            let removed = removed.clone();
+            async move {
+                let mut state = peer.state();
+                loop {
+                    peer.next().await;
+                    if peer.state() != state {
+                        state = peer.state();
+                        // nobody listening is not an error
+                        let _ = events.send(Event::Session {
+                            neighbor,
+                            state: state.clone(),
+                        });
+                    }
+                    if state == crate::state::State::Idle
+                        && removed.load(std::sync::atomic::Ordering::SeqCst)
+                    {
+                        return;
+                    }
+                }
+            }
+        });
+        self.neighbors.lock().unwrap().insert(
+            neighbor,
+            Neighbor {
+                handle,
+                removed,
+                task,
+                local,
+                listener,
+            },
+        );
+        Ok(())
+    }
+
+    // stops the session, which withdraws the routes learned from the neighbor, and
+    // returns whether the neighbor existed
+    pub async fn remove_neighbor(&self, remote_ip: std::net::IpAddr) -> bool {
+        let mut listeners = self.listeners.lock().await;
+        let Some(neighbor) = self.neighbors.lock().unwrap().remove(&remote_ip) else {
+            return false;
+        };
+        if let Some(listener) = &neighbor.listener {
+            crate::transport::Acceptor::unregister(listener, remote_ip);
+        }
+        // the local address is released once no neighbor uses it anymore
+        let in_use = self
+            .neighbors
+            .lock()
+            .unwrap()
+            .values()
+            .any(|other| other.local == neighbor.local);
+        if !in_use {
+            if let Some(bound) = listeners.remove(&neighbor.local) {
+                bound.task.abort();
+            }
+        }
+        drop(listeners);
+        neighbor
+            .removed
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        neighbor.handle.stop();
+        if let Err(e) = neighbor.task.await {
+            tracing::warn!("neighbor task failed: {e}");
+        }
+        true
+    }
+
+    pub fn neighbors(&self) -> Vec<std::net::IpAddr> {
+        self.neighbors.lock().unwrap().keys().copied().collect()
+    }
+
+    // originates a route from this speaker, replacing one injected for the same prefix
+    pub fn inject(&self, route: crate::route::Route) {
+        self.loc_rib
+            .lock()
+            .unwrap()
+            .insert(crate::rib::Candidate::new(
+                crate::rib::Source::local(self.router_id),
+                route,
+            ));
+    }
+
+    pub fn withdraw(&self, prefix: crate::packet::prefix::Prefix) {
+        self.loc_rib
+            .lock()
+            .unwrap()
+            .withdraw(std::net::Ipv4Addr::UNSPECIFIED.into(), &prefix);
+    }
+
+    pub fn best_routes(&self) -> Vec<crate::route::Route> {
+        self.loc_rib
+            .lock()
+            .unwrap()
+            .best_routes()
+            .cloned()
+            .collect()
+    }
+
+    // ends once the Loc-RIB is dropped together with the speaker and its neighbors
+    async fn report_best_routes(
+        loc_rib: std::sync::Weak<std::sync::Mutex<crate::rib::LocRib>>,
+        events: tokio::sync::broadcast::Sender<Event>,
+    ) {
+        let Some(mut changes) = loc_rib
+            .upgrade()
+            .map(|loc_rib| loc_rib.lock().unwrap().subscribe())
+        else {
+            return;
+        };
+        let mut reported = std::collections::HashMap::new();
+        while changes.changed().await.is_ok() {
+            let Some(loc_rib) = loc_rib.upgrade() else {
+                return;
+            };
+            let routes: std::collections::HashMap<_, _> = loc_rib
+                .lock()
+                .unwrap()
+                .best_routes()
+                .map(|route| (route.prefix, route.clone()))
+                .collect();
+            for prefix in reported.keys() {
+                if !routes.contains_key(prefix) {
+                    let _ = events.send(Event::BestRoute {
+                        prefix: *prefix,
+                        route: None,
+                    });
+                }
+            }
+            for (prefix, route) in &routes {
+                if reported.get(prefix) != Some(route) {
+                    let _ = events.send(Event::BestRoute {
+                        prefix: *prefix,
+                        route: Some(route.clone()),
+                    });
+                }
+            }
+            reported = routes;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt as _;
+    use std::str::FromStr as _;
+
+    async fn wait_for(events: &mut (impl futures::Stream<Item = Event> + Unpin), event: Event) {
+        while events.next().await.unwrap() != event {}
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn speakers_exchange_routes() {
+        let connector = crate::transport::MemoryConnector::new();
+        // the router id of the speaker wins over the one derived from the local address
+        let speaker = Speaker::with_connector("10.0.0.1".parse().unwrap(), connector.clone());
+        let remote_speaker = Speaker::with_connector("127.0.0.2".parse().unwrap(), connector);
+        let mut events = Box::pin(speaker.subscribe());
+        let mut remote_events = Box::pin(remote_speaker.subscribe());
+
+        remote_speaker
+            .add_neighbor(
+                crate::config::Config::from_str("64513 127.0.0.2 64512 127.0.0.1 passive").unwrap(),
+            )
+            .await
+            .unwrap();
+        let config =
+            crate::config::Config::from_str("64512 127.0.0.1 64513 127.0.0.2 active").unwrap();
+        speaker.add_neighbor(config.clone()).await.unwrap();
+        assert!(speaker.add_neighbor(config).await.is_err());
+        assert_eq!(
+            speaker.neighbors(),
+            vec![std::net::IpAddr::from([127, 0, 0, 2])]
+        );
+
+        let established = |neighbor: &str| Event::Session {
+            neighbor: neighbor.parse().unwrap(),
+            state: crate::state::State::Established,
+        };
+        wait_for(&mut events, established("127.0.0.2")).await;
+        wait_for(&mut remote_events, established("127.0.0.1")).await;
+
+        let prefix: crate::packet::prefix::Prefix = "10.100.210.0/24".parse().unwrap();
+        speaker.inject(crate::route::Route::local(prefix));
+        let route = loop {
+            if let Event::BestRoute {
+                prefix: p,
+                route: Some(route),
+            } = remote_events.next().await.unwrap()
+            {
+                assert_eq!(p, prefix);
+                break route;
+            }
+        };
+        assert_eq!(route.neighbor_as(), Some(64512.into()));
+        assert_eq!(remote_speaker.best_routes(), vec![route]);
+        assert_eq!(
+            remote_speaker.loc_rib().lock().unwrap().candidates(&prefix)[0]
+                .source
+                .router_id,
+            speaker.router_id()
+        );
+
+        speaker.withdraw(prefix);
+        wait_for(
+            &mut remote_events,
+            Event::BestRoute {
+                prefix,
+                route: None,
+            },
+        )
+        .await;
+
+        assert!(speaker.remove_neighbor("127.0.0.2".parse().unwrap()).await);
+        assert!(!speaker.remove_neighbor("127.0.0.2".parse().unwrap()).await);
+        assert!(speaker.neighbors().is_empty());
+        wait_for(
+            &mut remote_events,
+            Event::Session {
+                neighbor: "127.0.0.1".parse().unwrap(),
+                state: crate::state::State::Idle,
+            },
+        )
+        .await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn passive_neighbors_share_a_listener() {
+        let connector = crate::transport::MemoryConnector::new();
+        let speaker = Speaker::with_connector("127.0.0.1".parse().unwrap(), connector.clone());
+        let mut events = Box::pin(speaker.subscribe());
+        for remote_ip in ["127.0.0.2", "127.0.0.3"] {
+            speaker
+                .add_neighbor(
+                    crate::config::Config::from_str(&format!(
+                        "64512 127.0.0.1 64513 {remote_ip} passive"
+                    ))
+                    .unwrap(),
+                )
+                .await
+                .unwrap();
+        }
+
+        let mut remote_speakers = Vec::new();
+        for remote_ip in ["127.0.0.2", "127.0.0.3"] {
+            let remote_speaker =
+                Speaker::with_connector(remote_ip.parse().unwrap(), connector.clone());
+            remote_speaker
+                .add_neighbor(
+                    crate::config::Config::from_str(&format!(
+                        "64513 {remote_ip} 64512 127.0.0.1 active"
+                    ))
+                    .unwrap(),
+                )
+                .await
+                .unwrap();
+            remote_speakers.push(remote_speaker);
+        }
+        for remote_ip in ["127.0.0.2", "127.0.0.3"] {
+            wait_for(
+                &mut events,
+                Event::Session {
+                    neighbor: remote_ip.parse().unwrap(),
+                    state: crate::state::State::Established,
+                },
+            )
+            .await;
+        }
+
+        // the address is bound again once every neighbor on it is gone
+        for remote_ip in ["127.0.0.2", "127.0.0.3"] {
+            assert!(speaker.remove_neighbor(remote_ip.parse().unwrap()).await);
+        }
+        let local = "127.0.0.1:179".parse().unwrap();
+        assert!(crate::transport::Connector::bind(&connector, local)
+            .await
+            .is_ok());
+    }
+}
@@ -2,6 +2,8 @@
 pub enum State {
     Idle,
     Connect,
+    Active,
     OpenSent,
     OpenConfirm,
+    Established,
 }
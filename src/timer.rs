@@ -0,0 +1,103 @@
+#[derive(Debug, Clone)]
+pub struct Timer {
+    clock: crate::clock::SharedClock,
+    deadline: Option<std::time::Instant>,
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Self {
+            clock: std::sync::Arc::new(crate::clock::TokioClock),
+            deadline: None,
+        }
+    }
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    #[cfg(test)]
+    pub fn with_clock(clock: crate::clock::SharedClock) -> Self {
+        Self {
+            clock,
+            deadline: None,
+        }
+    }
+
+    pub fn start(&mut self, duration: std::time::Duration) {
+        self.deadline = Some(self.clock.now() + duration);
+    }
+
+    pub fn stop(&mut self) {
+        self.deadline = None;
+    }
+
+    // the time left until expiry, if the timer is running
+    #[cfg(test)]
+    pub fn remaining(&self) -> Option<std::time::Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(self.clock.now()))
+    }
+
+    pub async fn expired(&self) {
+        match self.deadline {
+            Some(deadline) => self.clock.sleep_until(deadline).await,
+            None => std::future::pending().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn timer_expires() {
+        let mut timer = Timer::new();
+        let timeout = std::time::Duration::from_secs(60);
+        assert!(tokio::time::timeout(timeout, timer.expired())
+            .await
+            .is_err());
+        assert_eq!(timer.remaining(), None);
+
+        let start = tokio::time::Instant::now();
+        timer.start(std::time::Duration::from_secs(30));
+        tokio::time::advance(std::time::Duration::from_secs(10)).await;
+        assert_eq!(timer.remaining(), Some(std::time::Duration::from_secs(20)));
+        assert!(tokio::time::timeout(timeout, timer.expired()).await.is_ok());
+        assert_eq!(
+            tokio::time::Instant::now() - start,
+            std::time::Duration::from_secs(30)
+        );
+
+        timer.stop();
+        assert!(tokio::time::timeout(timeout, timer.expired())
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn timer_follows_manual_clock() {
+        use futures::FutureExt as _;
+
+        let clock = crate::clock::ManualClock::new();
+        let mut timer = Timer::with_clock(std::sync::Arc::new(clock.clone()));
+        assert!(timer.expired().now_or_never().is_none());
+
+        timer.start(std::time::Duration::from_secs(30));
+        let mut expired = Box::pin(timer.expired());
+        clock.advance(std::time::Duration::from_secs(10));
+        assert_eq!(timer.remaining(), Some(std::time::Duration::from_secs(20)));
+        assert!(expired.as_mut().now_or_never().is_none());
+
+        // a pending expiry is woken up once the clock passes the deadline
+        clock.advance(std::time::Duration::from_secs(20));
+        assert!(expired.now_or_never().is_some());
+        assert_eq!(timer.remaining(), Some(std::time::Duration::ZERO));
+
+        timer.stop();
+        assert!(timer.expired().now_or_never().is_none());
+    }
+}
@@ -0,0 +1,295 @@
+use anyhow::Context as _;
+
+// any byte stream BGP messages can be exchanged over
+pub trait Transport:
+    tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + std::fmt::Debug + 'static
+{
+}
+
+impl<T> Transport for T where
+    T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + std::fmt::Debug + 'static
+{
+}
+
+// establishes the connection of a single peer, initiating it in active mode and
+// waiting for it in passive mode
+pub trait Connector: Clone + Send + Sync + std::fmt::Debug + 'static {
+    type Transport: Transport;
+    type Listener: Acceptor<Transport = Self::Transport>;
+
+    fn connect(
+        &self,
+        config: &crate::config::Config,
+    ) -> impl std::future::Future<Output = Result<Self::Transport, crate::error::ConnectionErr>>
+           + Send
+           + 'static;
+
+    fn accept(
+        &self,
+        config: &crate::config::Config,
+    ) -> impl std::future::Future<Output = Result<Self::Transport, crate::error::ConnectionErr>>
+           + Send
+           + 'static;
+
+    // binds a listener that peers on the same local address can share
+    fn bind(
+        &self,
+        local: std::net::SocketAddr,
+    ) -> impl std::future::Future<Output = Result<Self::Listener, crate::error::ConnectionErr>>
+           + Send
+           + 'static;
+}
+
+// accepts connections for many peers at once and hands each one to the peer
+// registered for its remote address
+pub trait Acceptor: Clone + Send + Sync + std::fmt::Debug + 'static {
+    type Transport: Transport;
+
+    // accepts connections until the returned future is dropped
+    fn run(&self) -> impl std::future::Future<Output = ()> + Send;
+
+    // replaces any peer previously registered for the same remote address
+    fn register(
+        &self,
+        remote_ip: std::net::IpAddr,
+    ) -> tokio::sync::mpsc::UnboundedReceiver<Self::Transport>;
+
+    fn unregister(&self, remote_ip: std::net::IpAddr);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TcpConnector;
+
+impl Connector for TcpConnector {
+    type Transport = tokio::net::TcpStream;
+    type Listener = crate::listener::Listener;
+
+    fn connect(
+        &self,
+        config: &crate::config::Config,
+    ) -> impl std::future::Future<Output = Result<Self::Transport, crate::error::ConnectionErr>>
+           + Send
+           + 'static {
+        let remote = std::net::SocketAddr::new(config.remote_ip, config.remote_port);
+        let update_source = config.update_source;
+        async move {
+            tracing::info!("connecting to remote peer {remote}");
+            let socket = match remote {
+                std::net::SocketAddr::V4(_) => tokio::net::TcpSocket::new_v4(),
+                std::net::SocketAddr::V6(_) => tokio::net::TcpSocket::new_v6(),
+            }
+            .context("failed to create socket")?;
+            if let Some(update_source) = update_source {
+                socket
+                    .bind(std::net::SocketAddr::new(update_source, 0))
+                    .context(format!("failed to bind to update source {update_source}"))?;
+            }
+            Ok(socket
+                .connect(remote)
+                .await
+                .context(format!("failed to connect to remote peer {remote}"))?)
+        }
+    }
+
+    fn accept(
+        &self,
+        config: &crate::config::Config,
+    ) -> impl std::future::Future<Output = Result<Self::Transport, crate::error::ConnectionErr>>
+           + Send
+           + 'static {
+        let local = std::net::SocketAddr::new(config.local_ip, config.listen_port);
+        async move {
+            let listener = tokio::net::TcpListener::bind(local)
+                .await
+                .context(format!("failed to bind to local peer {local}"))?;
+            let (stream, _) = listener
+                .accept()
+                .await
+                .context("failed to accept connection")?;
+            Ok(stream)
+        }
+    }
+
+    fn bind(
+        &self,
+        local: std::net::SocketAddr,
+    ) -> impl std::future::Future<Output = Result<Self::Listener, crate::error::ConnectionErr>>
+           + Send
+           + 'static {
+        crate::listener::Listener::bind(local)
+    }
+}
+
+type MemoryInbound =
+    tokio::sync::mpsc::UnboundedSender<(std::net::IpAddr, tokio::io::DuplexStream)>;
+
+// joins peers of the same process with in-memory streams, a passive peer being
+// reachable at its local address and listen port while it waits for a connection
+#[derive(Debug, Clone, Default)]
+pub struct MemoryConnector {
+    listeners: std::sync::Arc<
+        std::sync::Mutex<std::collections::HashMap<std::net::SocketAddr, MemoryInbound>>,
+    >,
+}
+
+impl MemoryConnector {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    fn connect_to(
+        &self,
+        source: std::net::IpAddr,
+        remote: std::net::SocketAddr,
+    ) -> Result<tokio::io::DuplexStream, crate::error::ConnectionErr> {
+        let (local, peer) = tokio::io::duplex(crate::constants::MEMORY_TRANSPORT_BUFFER);
+        let mut listeners = self.listeners.lock().unwrap();
+        let listener = listeners
+            .get(&remote)
+            .context(format!("connection refused by remote peer {remote}"))?;
+        if listener.send((source, peer)).is_err() {
+            listeners.remove(&remote);
+            return Err(anyhow::anyhow!("connection refused by remote peer {remote}").into());
+        }
+        Ok(local)
+    }
+}
+
+impl Connector for MemoryConnector {
+    type Transport = tokio::io::DuplexStream;
+    type Listener = MemoryListener;
+
+    fn connect(
+        &self,
+        config: &crate::config::Config,
+    ) -> impl std::future::Future<Output = Result<Self::Transport, crate::error::ConnectionErr>>
+           + Send
+           + 'static {
+        std::future::ready(self.connect_to(
+            config.update_source.unwrap_or(config.local_ip),
+            std::net::SocketAddr::new(config.remote_ip, config.remote_port),
+        ))
+    }
+
+    // the peer is reachable as soon as this is called, not only once the future is polled
+    fn accept(
+        &self,
+        config: &crate::config::Config,
+    ) -> impl std::future::Future<Output = Result<Self::Transport, crate::error::ConnectionErr>>
+           + Send
+           + 'static {
+        let local = std::net::SocketAddr::new(config.local_ip, config.listen_port);
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        self.listeners.lock().unwrap().insert(local, tx);
+        async move {
+            let (_, stream) = rx
+                .recv()
+                .await
+                .context(format!("stopped listening on {local}"))?;
+            Ok(stream)
+        }
+    }
+
+    fn bind(
+        &self,
+        local: std::net::SocketAddr,
+    ) -> impl std::future::Future<Output = Result<Self::Listener, crate::error::ConnectionErr>>
+           + Send
+           + 'static {
+        let mut listeners = self.listeners.lock().unwrap();
+        let listener = match listeners.get(&local) {
+            Some(listener) if !listener.is_closed() => {
+                Err(anyhow::anyhow!("failed to bind to {local}").into())
+            }
+            _ => {
+                let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+                listeners.insert(local, tx);
+                Ok(MemoryListener {
+                    incoming: std::sync::Arc::new(tokio::sync::Mutex::new(rx)),
+                    peers: Default::default(),
+                })
+            }
+        };
+        std::future::ready(listener)
+    }
+}
+
+// the in-memory counterpart of `crate::listener::Listener`
+#[derive(Debug, Clone)]
+pub struct MemoryListener {
+    incoming: std::sync::Arc<
+        tokio::sync::Mutex<
+            tokio::sync::mpsc::UnboundedReceiver<(std::net::IpAddr, tokio::io::DuplexStream)>,
+        >,
+    >,
+    peers: std::sync::Arc<
+        std::sync::Mutex<
+            std::collections::HashMap<
+                std::net::IpAddr,
+                tokio::sync::mpsc::UnboundedSender<tokio::io::DuplexStream>,
+            >,
+        >,
+    >,
+}
+
+impl Acceptor for MemoryListener {
+    type Transport = tokio::io::DuplexStream;
+
+    async fn run(&self) {
+        let mut incoming = self.incoming.lock().await;
+        while let Some((remote_ip, stream)) = incoming.recv().await {
+            let mut peers = self.peers.lock().unwrap();
+            let Some(peer) = peers.get(&remote_ip) else {
+                tracing::warn!("rejected connection from unknown peer {remote_ip}");
+                continue;
+            };
+            if peer.send(stream).is_err() {
+                peers.remove(&remote_ip);
+            }
+        }
+    }
+
+    fn register(
+        &self,
+        remote_ip: std::net::IpAddr,
+    ) -> tokio::sync::mpsc::UnboundedReceiver<Self::Transport> {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        self.peers.lock().unwrap().insert(remote_ip, tx);
+        rx
+    }
+
+    fn unregister(&self, remote_ip: std::net::IpAddr) {
+        self.peers.lock().unwrap().remove(&remote_ip);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr as _;
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    #[tokio::test]
+    async fn memory_connector_joins_peers() {
+        let connector = MemoryConnector::new();
+        let config =
+            crate::config::Config::from_str("64512 127.0.0.1 64513 127.0.0.2 active").unwrap();
+        let remote_config =
+            crate::config::Config::from_str("64513 127.0.0.2 64512 127.0.0.1 passive").unwrap();
+
+        // nothing is listening yet
+        assert!(connector.connect(&config).await.is_err());
+
+        let accept = connector.accept(&remote_config);
+        let mut stream = connector.connect(&config).await.unwrap();
+        let mut remote_stream = accept.await.unwrap();
+        stream.write_all(b"ekkyo").await.unwrap();
+        let mut buf = [0; 5];
+        remote_stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ekkyo");
+
+        // the listener is gone once the waiting peer gives up
+        drop(connector.accept(&remote_config));
+        assert!(connector.connect(&config).await.is_err());
+    }
+}
@@ -1,25 +1,95 @@
-#[derive(Debug, Clone, Hash, PartialEq, Eq, Copy)]
-pub struct ASNum(u16);
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Copy, PartialOrd, Ord)]
+pub struct ASNum(u32);
 
-impl From<ASNum> for u16 {
-    fn from(asn: ASNum) -> u16 {
+impl ASNum {
+    // RFC 6793 reserves AS_TRANS for four-octet AS numbers in two-octet fields
+    pub const AS_TRANS: ASNum = ASNum(23456);
+
+    pub fn is_four_octet(&self) -> bool {
+        self.0 > u16::MAX as u32
+    }
+
+    pub fn to_two_octet(self) -> u16 {
+        u16::try_from(self.0).unwrap_or(Self::AS_TRANS.0 as u16)
+    }
+}
+
+impl From<ASNum> for u32 {
+    fn from(asn: ASNum) -> u32 {
         asn.0
     }
 }
 
-impl From<u16> for ASNum {
-    fn from(asn: u16) -> ASNum {
+impl From<u32> for ASNum {
+    fn from(asn: u32) -> ASNum {
         ASNum(asn)
     }
 }
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq, Default)]
+// accepts both asplain (`65546`) and asdot (`1.10`) notations as in RFC 5396
+impl std::str::FromStr for ASNum {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('.') {
+            Some((high, low)) => {
+                let high: u16 = high.parse()?;
+                let low: u16 = low.parse()?;
+                Ok(ASNum((high as u32) << 16 | low as u32))
+            }
+            None => Ok(ASNum(s.parse()?)),
+        }
+    }
+}
+
+// configuration files may use either an integer or an asdot string
+impl<'de> serde::Deserialize<'de> for ASNum {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Plain(u32),
+            Text(String),
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Plain(asn) => Ok(ASNum(asn)),
+            Repr::Text(asn) => asn.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+impl std::fmt::Display for ASNum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize)]
+#[serde(try_from = "u16")]
 pub struct HoldTime(u16);
 
 impl HoldTime {
     pub fn new() -> Self {
         Default::default()
     }
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn keepalive_interval(&self) -> std::time::Duration {
+        std::time::Duration::from(*self) / 3
+    }
+}
+
+impl Default for HoldTime {
+    fn default() -> Self {
+        HoldTime(90)
+    }
 }
 
 impl From<HoldTime> for u16 {
@@ -28,9 +98,22 @@ impl From<HoldTime> for u16 {
     }
 }
 
-impl From<u16> for HoldTime {
-    fn from(ht: u16) -> HoldTime {
-        HoldTime(ht)
+// RFC 4271 allows a hold time of zero or at least three seconds
+impl TryFrom<u16> for HoldTime {
+    type Error = anyhow::Error;
+
+    fn try_from(ht: u16) -> Result<Self, Self::Error> {
+        if ht == 1 || ht == 2 {
+            return Err(anyhow::anyhow!("unacceptable hold time: {ht}"));
+        }
+
+        Ok(HoldTime(ht))
+    }
+}
+
+impl From<HoldTime> for std::time::Duration {
+    fn from(ht: HoldTime) -> std::time::Duration {
+        std::time::Duration::from_secs(ht.0.into())
     }
 }
 
@@ -59,10 +142,89 @@ impl TryFrom<u8> for Version {
     type Error = anyhow::Error;
 
     fn try_from(v: u8) -> Result<Self, Self::Error> {
-        if v > 4 {
-            return Err(anyhow::anyhow!("invalid version"));
+        if v != 4 {
+            return Err(anyhow::anyhow!("unsupported version: {v}"));
         }
 
         Ok(Version(v))
     }
 }
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Afi {
+    Ipv4,
+    Ipv6,
+}
+
+impl TryFrom<u16> for Afi {
+    type Error = anyhow::Error;
+
+    fn try_from(afi: u16) -> Result<Self, Self::Error> {
+        match afi {
+            1 => Ok(Afi::Ipv4),
+            2 => Ok(Afi::Ipv6),
+            _ => Err(anyhow::anyhow!("unsupported AFI: {afi}")),
+        }
+    }
+}
+
+impl From<Afi> for u16 {
+    fn from(afi: Afi) -> u16 {
+        match afi {
+            Afi::Ipv4 => 1,
+            Afi::Ipv6 => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Safi {
+    Unicast,
+}
+
+impl TryFrom<u8> for Safi {
+    type Error = anyhow::Error;
+
+    fn try_from(safi: u8) -> Result<Self, Self::Error> {
+        match safi {
+            1 => Ok(Safi::Unicast),
+            _ => Err(anyhow::anyhow!("unsupported SAFI: {safi}")),
+        }
+    }
+}
+
+impl From<Safi> for u8 {
+    fn from(safi: Safi) -> u8 {
+        match safi {
+            Safi::Unicast => 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn asnum_can_parse() {
+        assert_eq!("64512".parse::<ASNum>().unwrap(), ASNum::from(64512));
+        assert_eq!(
+            "4200000000".parse::<ASNum>().unwrap(),
+            ASNum::from(4200000000)
+        );
+        assert_eq!("1.10".parse::<ASNum>().unwrap(), ASNum::from(65546));
+        assert_eq!("0.64512".parse::<ASNum>().unwrap(), ASNum::from(64512));
+        assert!("4294967296".parse::<ASNum>().is_err());
+        assert!("65536.0".parse::<ASNum>().is_err());
+        assert!("1.".parse::<ASNum>().is_err());
+        assert!("foo".parse::<ASNum>().is_err());
+    }
+
+    #[test]
+    fn asnum_to_two_octet() {
+        assert_eq!(ASNum::from(64512).to_two_octet(), 64512);
+        assert_eq!(ASNum::from(65546).to_two_octet(), 23456);
+        assert!(!ASNum::from(65535).is_four_octet());
+        assert!(ASNum::from(65536).is_four_octet());
+    }
+}